@@ -0,0 +1,214 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+// the colors of SGR codes 30 to 37 and 90 to 97, in that order
+const COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    // the parameters of a SGR sequence, e.g. `1;31` for `ESC[1;31m`
+    Sgr(&'a str),
+}
+
+/// Splits `s` into text and SGR sequences. Other escape sequences, e.g. ones that move the
+/// cursor or set the window title, are dropped.
+fn segments(s: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('\x1b') {
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        let sequence = &rest[start + 1..];
+        let length = match sequence.chars().next() {
+            Some('[') => match sequence[1..].find(|c: char| ('@'..='~').contains(&c)) {
+                Some(end) => {
+                    if sequence[1 + end..].starts_with('m') {
+                        segments.push(Segment::Sgr(&sequence[1..1 + end]));
+                    }
+                    end + 2
+                }
+                None => sequence.len(),
+            },
+            // operating system commands end with BEL or `ESC \`
+            Some(']') => match sequence.find(['\x07', '\x1b']) {
+                Some(end) if sequence[end..].starts_with('\x07') => end + 1,
+                Some(end) if sequence[end..].starts_with("\x1b\\") => end + 2,
+                Some(end) => end,
+                None => sequence.len(),
+            },
+            Some(c) => c.len_utf8(),
+            None => 0,
+        };
+        rest = &sequence[length..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// Reads the color of a `38;…` or `48;…` code, either `5;n` for the 256 color palette or
+/// `2;r;g;b`.
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()? as u8)),
+        2 => Some(Color::Rgb(
+            codes.next()? as u8,
+            codes.next()? as u8,
+            codes.next()? as u8,
+        )),
+        _ => None,
+    }
+}
+
+fn apply_sgr(mut style: Style, parameters: &str) -> Style {
+    let mut codes = parameters.split([';', ':']).filter_map(|code| match code {
+        "" => Some(0),
+        code => code.parse::<u16>().ok(),
+    });
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(COLORS[code as usize - 30]),
+            90..=97 => style.fg(COLORS[code as usize - 90 + 8]),
+            40..=47 => style.bg(COLORS[code as usize - 40]),
+            100..=107 => style.bg(COLORS[code as usize - 100 + 8]),
+            38 => extended_color(&mut codes).map_or(style, |color| style.fg(color)),
+            48 => extended_color(&mut codes).map_or(style, |color| style.bg(color)),
+            // the default colors are the ones of the pane
+            39 => Style { fg: None, ..style },
+            49 => Style { bg: None, ..style },
+            _ => style,
+        };
+    }
+    style
+}
+
+/// `s` without any escape sequences, i.e. the text as it appears on screen.
+pub(crate) fn strip(s: &str) -> String {
+    if !s.contains('\x1b') {
+        return s.to_string();
+    }
+    segments(s)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            Segment::Sgr(_) => None,
+        })
+        .collect()
+}
+
+/// `s` with the colors and attributes its SGR sequences set. Unstyled text keeps the style of
+/// the widget it is rendered in.
+pub(crate) fn text(s: &str) -> Text<'static> {
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    for segment in segments(s) {
+        match segment {
+            Segment::Sgr(parameters) => style = apply_sgr(style, parameters),
+            Segment::Text(text) => {
+                let mut pieces = text.split('\n').peekable();
+                while let Some(piece) = pieces.next() {
+                    let ends_line = pieces.peek().is_some();
+                    let piece = match ends_line {
+                        true => piece.strip_suffix('\r').unwrap_or(piece),
+                        false => piece,
+                    };
+                    if !piece.is_empty() {
+                        spans.push(Span::styled(piece.to_string(), style));
+                    }
+                    if ends_line {
+                        lines.push(Line::from(std::mem::take(&mut spans)));
+                    }
+                }
+            }
+        }
+    }
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        style::{Color, Modifier, Style},
+        text::Span,
+    };
+
+    use super::{strip, text};
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("plain"), "plain");
+        assert_eq!(strip("\x1b[1;34msrc\x1b[0m  main.rs"), "src  main.rs");
+        assert_eq!(strip("\x1b]0;title\x07done\x1b[2K"), "done");
+        assert_eq!(strip("\x1b]8;;https://example.com\x1b\\link"), "link");
+    }
+
+    #[test]
+    fn test_text() {
+        let text = text("\x1b[1;31merror\x1b[0m: oops\n\x1b[38;5;208mwarn\x1b[39mx");
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(
+            text.lines[0].spans,
+            [
+                Span::styled(
+                    "error",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                ),
+                Span::raw(": oops"),
+            ]
+        );
+        assert_eq!(
+            text.lines[1].spans,
+            [
+                Span::styled("warn", Style::default().fg(Color::Indexed(208))),
+                Span::raw("x"),
+            ]
+        );
+        assert_eq!(
+            super::text("\x1b[48;2;1;2;3mrgb\x1b[m").lines[0].spans,
+            [Span::styled(
+                "rgb",
+                Style::default().bg(Color::Rgb(1, 2, 3))
+            )]
+        );
+    }
+}
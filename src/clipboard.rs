@@ -0,0 +1,66 @@
+/// The system clipboard, or a buffer that only vshell can see when there is none, e.g. over
+/// ssh without a display. Copying and pasting then still works within vshell.
+pub(crate) struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    internal: String,
+    // the last operation had to fall back to the internal buffer
+    fell_back: bool,
+}
+
+impl Clipboard {
+    pub(crate) fn new() -> Self {
+        Clipboard {
+            system: arboard::Clipboard::new().ok(),
+            internal: String::new(),
+            fell_back: false,
+        }
+    }
+
+    pub(crate) fn set_text(&mut self, text: String) {
+        let copied = match &mut self.system {
+            Some(system) => system.set_text(text.as_str()).is_ok(),
+            None => false,
+        };
+        self.fell_back = !copied;
+        self.internal = text;
+    }
+
+    pub(crate) fn get_text(&mut self) -> String {
+        let pasted = match &mut self.system {
+            Some(system) => system.get_text().ok(),
+            None => None,
+        };
+        self.fell_back = pasted.is_none();
+        pasted.unwrap_or_else(|| self.internal.clone())
+    }
+
+    /// A notice for the user if the last operation could not use the system clipboard.
+    pub(crate) fn take_notice(&mut self) -> Option<&'static str> {
+        match std::mem::take(&mut self.fell_back) {
+            true => Some("system clipboard unavailable, using vshell's own clipboard"),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_internal_clipboard() {
+        let mut clipboard = Clipboard {
+            system: None,
+            internal: String::new(),
+            fell_back: false,
+        };
+        assert_eq!(clipboard.take_notice(), None);
+
+        clipboard.set_text("ls -la".to_string());
+        assert!(clipboard.take_notice().is_some());
+        assert_eq!(clipboard.take_notice(), None);
+
+        assert_eq!(clipboard.get_text(), "ls -la");
+        assert!(clipboard.take_notice().is_some());
+    }
+}
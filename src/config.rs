@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use crate::Model;
+
+/// Where settings are read from at startup, `~/.config/vshell/config.toml`.
+pub(crate) fn default_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("vshell").join("config.toml"))
+}
+
+/// Applies the settings in the file at `path` to `model`. Top level keys are the ones `:set`
+/// takes, the `aliases` and `abbreviations` tables map names to commands and the `theme` table
+/// maps its fields to colors. A missing file is
+/// not an error, everything else that goes wrong is returned as one message per line.
+pub(crate) fn load(path: &Path, model: &mut Model) -> Result<(), String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => apply(&contents, model),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+    }
+}
+
+fn apply(contents: &str, model: &mut Model) -> Result<(), String> {
+    let table = contents
+        .parse::<toml::Table>()
+        .map_err(|e| format!("invalid config: {}", e))?;
+
+    let mut errors = Vec::new();
+    for (key, value) in table {
+        let result = match (key.as_str(), value) {
+            ("aliases", toml::Value::Table(aliases)) => {
+                commands(aliases).map(|aliases| model.aliases.extend(aliases))
+            }
+            ("abbreviations", toml::Value::Table(abbreviations)) => commands(abbreviations)
+                .map(|abbreviations| model.config.abbreviations.extend(abbreviations)),
+            ("theme", toml::Value::Table(colors)) => {
+                colors
+                    .into_iter()
+                    .try_for_each(|(field, color)| match color {
+                        toml::Value::String(color) => {
+                            model.config.set(&format!("theme.{}", field), &color)
+                        }
+                        color => Err(format!("expected a color for {} but got {}", field, color)),
+                    })
+            }
+            (_, toml::Value::String(value)) => model.config.set(&key, &value),
+            (_, toml::Value::Boolean(value)) => model.config.set(&key, &value.to_string()),
+            (_, toml::Value::Integer(value)) => model.config.set(&key, &value.to_string()),
+            (_, toml::Value::Float(value)) => model.config.set(&key, &value.to_string()),
+            (_, value) => Err(format!("unexpected value {}", value)),
+        };
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", key, e));
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+fn commands(table: toml::Table) -> Result<Vec<(String, String)>, String> {
+    table
+        .into_iter()
+        .map(|(name, command)| match command {
+            toml::Value::String(command) => Ok((name, command)),
+            command => Err(format!(
+                "expected a command for {} but got {}",
+                name, command
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ratatui::style::Color;
+
+    use super::apply;
+    use crate::{HintState, HistoryType, Model, Theme};
+
+    #[test]
+    fn test_apply() {
+        let mut model = Model::default();
+        let config = r##"
+            hint_state = "show"
+            history_type = "directories"
+            shell = "zsh"
+            stream_output = true
+
+            [aliases]
+            ll = "ls -la"
+
+            [theme]
+            background = "#1e1e1e"
+            cursor = "yellow"
+        "##;
+        assert_eq!(apply(config, &mut model), Ok(()));
+        assert_eq!(model.config.hint_state, HintState::ShowHints);
+        assert_eq!(model.config.history_type, HistoryType::DirectoryHistory);
+        assert_eq!(model.config.shell, "zsh");
+        assert_eq!(model.config.theme.background, Color::Rgb(30, 30, 30));
+        assert_eq!(model.config.theme.cursor, Color::Yellow);
+        assert_eq!(model.config.theme.foreground, Theme::default().foreground);
+        assert!(model.config.stream_output);
+        assert_eq!(model.aliases["ll"], "ls -la");
+    }
+
+    #[test]
+    fn test_apply_numbers() {
+        let mut model = Model::default();
+        assert_eq!(apply("idle_exit = 10", &mut model), Ok(()));
+        assert_eq!(model.config.idle_exit, Some(Duration::from_secs(600)));
+        assert_eq!(apply("idle_exit = 1.5", &mut model), Ok(()));
+        assert_eq!(model.config.idle_exit, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let mut model = Model::default();
+        assert!(apply("hint_state = ", &mut model).is_err());
+
+        let config = "hint_state = \"loud\"\npaste_guard = false\nshow_hidden = 3";
+        let errors = apply(config, &mut model).unwrap_err();
+        assert_eq!(errors.lines().count(), 2);
+        // the valid settings are applied anyway
+        assert!(!model.config.paste_guard);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{fmt, sync::PoisonError};
+
+/// Errors that end the vshell session. Problems with a single command are shown in the
+/// output pane instead.
+#[derive(Debug)]
+pub(crate) enum VshellError {
+    /// a thread panicked while holding the model lock
+    LockPoisoned,
+    /// a thread executing a command panicked
+    ThreadPanicked,
+    Io(std::io::Error),
+    InvalidHint(&'static str),
+    /// vshell was started with an argument it does not understand
+    InvalidArgument(String),
+}
+
+impl fmt::Display for VshellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VshellError::LockPoisoned => write!(f, "a thread panicked while holding the lock"),
+            VshellError::ThreadPanicked => write!(f, "a command thread panicked"),
+            VshellError::Io(e) => write!(f, "io error: {}", e),
+            VshellError::InvalidHint(e) => write!(f, "invalid hint: {}", e),
+            VshellError::InvalidArgument(e) => write!(f, "invalid argument: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VshellError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VshellError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for VshellError {
+    fn from(_: PoisonError<T>) -> Self {
+        VshellError::LockPoisoned
+    }
+}
+
+impl From<std::io::Error> for VshellError {
+    fn from(e: std::io::Error) -> Self {
+        VshellError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn poisoned_lock() {
+        let lock = Arc::new(Mutex::new(0));
+        let thread_lock = Arc::clone(&lock);
+        let _ = std::thread::spawn(move || {
+            let _guard = thread_lock.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        let result: Result<(), VshellError> = lock.lock().map(|_| ()).map_err(Into::into);
+        assert!(matches!(result, Err(VshellError::LockPoisoned)));
+    }
+}
@@ -1,19 +1,32 @@
 #[derive(Debug, PartialEq)]
 pub(crate) enum Event {
     CtrlC,
+    CtrlL,
+    CtrlU,
+    CtrlO,
+    CtrlW,
+    CtrlK,
+    CtrlY,
     Backspace,
     Esc,
     Enter,
+    Tab,
     Up,
     Down,
+    PageUp,
+    PageDown,
     Left,
     Right,
+    AltLeft,
+    AltRight,
+    Home,
+    End,
     Character(char),
     MouseDown(u16, u16),
     Paste(String),
 }
 
-pub(crate) fn get_event() -> Result<Option<Event>, Box<dyn std::error::Error>> {
+pub(crate) fn get_event() -> std::io::Result<Option<Event>> {
     if crossterm::event::poll(std::time::Duration::from_millis(32))? {
         Ok(create_event(crossterm::event::read()?))
     } else {
@@ -31,13 +44,69 @@ fn create_event(crossterm_event: crossterm::event::Event) -> Option<Event> {
                     {
                         Some(Event::CtrlC)
                     }
+                    crossterm::event::KeyCode::Char('l')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlL)
+                    }
+                    crossterm::event::KeyCode::Char('u')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlU)
+                    }
+                    crossterm::event::KeyCode::Char('o')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlO)
+                    }
+                    crossterm::event::KeyCode::Char('w')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlW)
+                    }
+                    crossterm::event::KeyCode::Char('k')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlK)
+                    }
+                    crossterm::event::KeyCode::Char('y')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::CtrlY)
+                    }
+                    crossterm::event::KeyCode::Char('a')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::Home)
+                    }
+                    crossterm::event::KeyCode::Char('e')
+                        if key.modifiers == crossterm::event::KeyModifiers::CONTROL =>
+                    {
+                        Some(Event::End)
+                    }
+                    // terminals that treat alt as meta send alt+b and alt+f instead
+                    crossterm::event::KeyCode::Left | crossterm::event::KeyCode::Char('b')
+                        if key.modifiers == crossterm::event::KeyModifiers::ALT =>
+                    {
+                        Some(Event::AltLeft)
+                    }
+                    crossterm::event::KeyCode::Right | crossterm::event::KeyCode::Char('f')
+                        if key.modifiers == crossterm::event::KeyModifiers::ALT =>
+                    {
+                        Some(Event::AltRight)
+                    }
+                    crossterm::event::KeyCode::Home => Some(Event::Home),
+                    crossterm::event::KeyCode::End => Some(Event::End),
                     crossterm::event::KeyCode::Left => Some(Event::Left),
                     crossterm::event::KeyCode::Right => Some(Event::Right),
                     crossterm::event::KeyCode::Backspace => Some(Event::Backspace),
                     crossterm::event::KeyCode::Esc => Some(Event::Esc),
                     crossterm::event::KeyCode::Enter => Some(Event::Enter),
+                    crossterm::event::KeyCode::Tab => Some(Event::Tab),
                     crossterm::event::KeyCode::Up => Some(Event::Up),
                     crossterm::event::KeyCode::Down => Some(Event::Down),
+                    crossterm::event::KeyCode::PageUp => Some(Event::PageUp),
+                    crossterm::event::KeyCode::PageDown => Some(Event::PageDown),
                     crossterm::event::KeyCode::Char(c) => Some(Event::Character(c)),
                     _ => None,
                 }
@@ -55,3 +124,45 @@ fn create_event(crossterm_event: crossterm::event::Event) -> Option<Event> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{create_event, Event};
+
+    #[test]
+    fn test_editing_keys() {
+        let key = |code, modifiers| {
+            create_event(crossterm::event::Event::Key(KeyEvent::new(code, modifiers)))
+        };
+        assert_eq!(key(KeyCode::Home, KeyModifiers::NONE), Some(Event::Home));
+        assert_eq!(key(KeyCode::End, KeyModifiers::NONE), Some(Event::End));
+        assert_eq!(
+            key(KeyCode::Char('a'), KeyModifiers::CONTROL),
+            Some(Event::Home)
+        );
+        assert_eq!(
+            key(KeyCode::Char('e'), KeyModifiers::CONTROL),
+            Some(Event::End)
+        );
+        assert_eq!(
+            key(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            Some(Event::CtrlW)
+        );
+        assert_eq!(
+            key(KeyCode::Char('k'), KeyModifiers::CONTROL),
+            Some(Event::CtrlK)
+        );
+        assert_eq!(key(KeyCode::Left, KeyModifiers::ALT), Some(Event::AltLeft));
+        assert_eq!(
+            key(KeyCode::Char('f'), KeyModifiers::ALT),
+            Some(Event::AltRight)
+        );
+        assert_eq!(key(KeyCode::Left, KeyModifiers::NONE), Some(Event::Left));
+        assert_eq!(
+            key(KeyCode::Char('a'), KeyModifiers::NONE),
+            Some(Event::Character('a'))
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{CompletedCommand, Output, OutputType};
+
+/// Where the command history is kept between sessions, usually
+/// `~/.local/share/vshell/history`.
+pub(crate) fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|data_dir| data_dir.join("vshell").join("history"))
+}
+
+/// Where the directory history is kept between sessions, next to the command history.
+pub(crate) fn default_directories_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|data_dir| data_dir.join("vshell").join("directories"))
+}
+
+/// Reads the directories visited in earlier sessions, oldest first. Only the most recent
+/// visit of each directory is kept and directories that no longer exist are left out.
+pub(crate) fn load_directories(path: &Path) -> Vec<PathBuf> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    dedup_directories(contents.lines().map(PathBuf::from))
+        .into_iter()
+        .filter(|directory| directory.is_dir())
+        .collect()
+}
+
+/// Replaces the saved directory history with `directories`.
+pub(crate) fn save_directories(path: &Path, directories: &[PathBuf]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = directories
+        .iter()
+        .map(|directory| format!("{}\n", directory.to_string_lossy()))
+        .collect::<String>();
+    std::fs::write(path, contents)
+}
+
+fn dedup_directories(directories: impl DoubleEndedIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut deduped = directories
+        .rev()
+        .filter(|directory| seen.insert(directory.clone()))
+        .collect::<Vec<PathBuf>>();
+    deduped.reverse();
+    deduped
+}
+
+/// Reads the history written by earlier sessions. Outputs are not kept, only whether the
+/// command succeeded. A missing or unreadable file is an empty history and lines that cannot
+/// be parsed are skipped.
+pub(crate) fn load(path: &Path) -> Vec<CompletedCommand> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents.lines().filter_map(parse_entry).collect()
+}
+
+/// Appends a single command to the history file, creating it if necessary.
+pub(crate) fn append(path: &Path, completed_command: &CompletedCommand) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", format_entry(completed_command))
+}
+
+/// Replaces the history file with `completed_commands`.
+pub(crate) fn save(path: &Path, completed_commands: &[CompletedCommand]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = completed_commands
+        .iter()
+        .map(|completed_command| format!("{}\n", format_entry(completed_command)))
+        .collect::<String>();
+    std::fs::write(path, contents)
+}
+
+/// Forgets the history of earlier sessions.
+pub(crate) fn clear(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// one command per line: the kind of output, a tab and the input with newlines escaped
+fn format_entry(completed_command: &CompletedCommand) -> String {
+    let kind = match completed_command.output.output_type {
+        OutputType::Success(_, _, _) => "success",
+        OutputType::Error(_, _, _) => "error",
+        OutputType::Empty => "empty",
+    };
+    let input = completed_command
+        .input
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n");
+    format!("{}\t{}", kind, input)
+}
+
+fn parse_entry(line: &str) -> Option<CompletedCommand> {
+    let (kind, input) = line.split_once('\t')?;
+    let output_type = match kind {
+        "success" => OutputType::Success(String::new(), String::new(), None),
+        "error" => OutputType::Error(String::new(), String::new(), None),
+        "empty" => OutputType::Empty,
+        _ => return None,
+    };
+    let mut unescaped = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => unescaped.push('\n'),
+                '\\' => unescaped.push('\\'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    if unescaped.is_empty() {
+        return None;
+    }
+    Some(CompletedCommand {
+        input: unescaped,
+        output: Output {
+            output_type,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn entries_survive_a_round_trip() {
+        let completed_command = CompletedCommand {
+            input: "echo 'a\\b\nc'".to_string(),
+            output: Output {
+                output_type: OutputType::Error("out".to_string(), "err".to_string(), Some(1)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let parsed = parse_entry(&format_entry(&completed_command)).unwrap();
+        assert_eq!(parsed.input, completed_command.input);
+        assert_eq!(
+            parsed.output.output_type,
+            OutputType::Error(String::new(), String::new(), None)
+        );
+    }
+
+    #[test]
+    fn directories_keep_their_latest_visit() {
+        let directories = ["/a", "/b", "/a", "/c"].map(PathBuf::from);
+        assert_eq!(
+            dedup_directories(directories.into_iter()),
+            ["/b", "/a", "/c"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn corrupt_entries_are_skipped() {
+        assert!(parse_entry("").is_none());
+        assert!(parse_entry("no tab").is_none());
+        assert!(parse_entry("unknown\tls").is_none());
+        assert!(parse_entry("success\tbad \\x escape").is_none());
+        assert!(parse_entry("success\t").is_none());
+    }
+}
@@ -1,61 +1,212 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     ffi::OsString,
     fmt,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
     thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use arboard::Clipboard;
-use ratatui::layout::Rect;
+use clipboard::Clipboard;
+use error::VshellError;
+use ratatui::{layout::Rect, style::Color};
 
+mod ansi;
+mod clipboard;
+mod config;
+mod error;
 mod event;
+mod history;
 mod tui;
 mod update;
 mod view;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<(), VshellError> {
     tui::install_panic_hook();
     let run_result = run();
     tui::restore_terminal()?;
-    run_result
+    // the output of `-C` is printed once the terminal is back to normal
+    if let Some(output) = run_result? {
+        match output.output_type {
+            OutputType::Success(stdout, stderr, _) => {
+                print!("{}", stdout);
+                eprint!("{}", stderr);
+            }
+            OutputType::Error(stdout, stderr, exit_code) => {
+                print!("{}", stdout);
+                eprint!("{}", stderr);
+                std::process::exit(exit_code.unwrap_or(1));
+            }
+            OutputType::Empty => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Arguments {
+    // `--key value`, e.g. `--idle-exit 10`
+    settings: Vec<(String, String)>,
+    // run at launch, see `-c`
+    command: Option<String>,
+    // with `-C` the output of `command` is printed and vshell exits
+    exit_after_command: bool,
+}
+
+/// Reads the command line. Settings are given as `--key value` and take the same keys as `:set`
+/// with dashes instead of underscores, they override the config file. `-c <command>` runs the
+/// command at launch and `-C <command>` prints its output and exits, like `sh -c`.
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, VshellError> {
+    let mut arguments = Arguments::default();
+    while let Some(arg) = args.next() {
+        if arg == "-c" || arg == "-C" {
+            let command = args
+                .next()
+                .ok_or_else(|| VshellError::InvalidArgument(format!("{} needs a command", arg)))?;
+            if arguments.command.is_some() {
+                return Err(VshellError::InvalidArgument(format!("{} given twice", arg)));
+            }
+            arguments.command = Some(command);
+            arguments.exit_after_command = arg == "-C";
+            continue;
+        }
+        let Some(key) = arg.strip_prefix("--").map(|key| key.replace('-', "_")) else {
+            return Err(VshellError::InvalidArgument(arg));
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| VshellError::InvalidArgument(format!("{} needs a value", arg)))?;
+        // checked right away so a typo is reported before the terminal is taken over
+        Config::default()
+            .set(&key, &value)
+            .map_err(|e| VshellError::InvalidArgument(format!("{}: {}", arg, e)))?;
+        arguments.settings.push((key, value));
+    }
+    Ok(arguments)
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut clipboard = Clipboard::new()?;
+/// Runs the interactive session. The output of the command given with `-C` is returned so it
+/// can be printed after the terminal is restored.
+fn run() -> Result<Option<Output>, VshellError> {
+    let arguments = parse_arguments(std::env::args().skip(1))?;
+    let mut clipboard = Clipboard::new();
     let mut terminal = tui::init_terminal()?;
     let model = Arc::new(Mutex::new(Model::default()));
+    let directories_file = history::default_directories_path();
+    let mut directory_history = directories_file
+        .as_deref()
+        .map(history::load_directories)
+        .unwrap_or_default();
+    let current_dir = std::env::current_dir()?;
+    if directory_history.last() != Some(&current_dir) {
+        directory_history.push(current_dir);
+    }
     // SAFETY: no one has panicked while holding the mutex yet since
     // this is the first access -> unwrap is ok
-    model
-        .lock()
-        .unwrap()
-        .directory_history
-        .push(std::env::current_dir()?);
+    model.lock().unwrap().directory_history = directory_history;
     model.lock().unwrap().config.hint_state = HintState::HideHints;
+    if let Some(config_file) = config::default_path() {
+        let mut model = model.lock()?;
+        if let Err(e) = config::load(&config_file, &mut model) {
+            model.current_command = CurrentView::Output(Output {
+                origin: Origin::Vshell,
+                output_type: OutputType::Error(String::new(), e, None),
+            });
+        }
+    }
+    for (key, value) in arguments.settings {
+        // SAFETY: every setting was checked by `parse_arguments`
+        model.lock()?.config.set(&key, &value).unwrap();
+    }
+    {
+        let mut model = model.lock()?;
+        if model.config.check_shell && update::find_executable(&model.config.shell).is_none() {
+            model.status = Some(Status::Error(format!(
+                "shell {} not found, :se will not work",
+                model.config.shell
+            )));
+        }
+    }
+    if let Some(history_file) = history::default_path() {
+        let mut model = model.lock()?;
+        for completed_command in history::load(&history_file) {
+            model.push_to_history(completed_command);
+        }
+        model.command_history_index = model.command_history.len();
+        model.history_file = Some(history_file);
+    }
+    let history_length = model.lock()?.command_history.len();
+    if let Some(command) = arguments.command {
+        model
+            .lock()?
+            .set_current_view_from_command(command.len() as u64, command);
+        update(&model, event::Event::Enter, &mut clipboard)?;
+    }
 
+    let mut printed_output = None;
+    let mut last_input = Instant::now();
     loop {
         {
-            let mut model = model.lock().map_err(|_| "lock failed")?;
+            let mut model = model.lock()?;
             terminal.draw(|frame| view::view(&mut model, frame))?;
         }
 
         let model = Arc::clone(&model);
         let event = event::get_event()?;
         if let Some(event) = event {
-            update::update(&model, event, &mut clipboard)?;
+            last_input = Instant::now();
+            update(&model, event, &mut clipboard)?;
         }
-        if model.lock().map_err(|_| "lock failed")?.should_quit() {
+        if model.lock()?.should_quit() {
             break;
         }
         while let Some(next_event) = event::get_event()? {
-            update::update(&model, next_event, &mut clipboard)?;
+            last_input = Instant::now();
+            update(&model, next_event, &mut clipboard)?;
+        }
+        let mut model = model.lock()?;
+        // the time without input counts from when the last running command finished
+        if model.is_running() {
+            last_input = Instant::now();
+        }
+        if model.is_idle_for_too_long(last_input.elapsed()) {
+            model.mode = Mode::Quit;
+        }
+        // a command that asks for confirmation first is waited for as well
+        if arguments.exit_after_command && matches!(model.mode, Mode::Idle) {
+            if model.command_history.len() > history_length {
+                printed_output = model
+                    .command_history
+                    .last()
+                    .map(|completed_command| completed_command.output.clone());
+            }
+            break;
         }
-        if model.lock().map_err(|_| "lock failed")?.should_quit() {
+        if model.should_quit() {
             break;
         }
     }
 
+    if let Some(directories_file) = directories_file {
+        history::save_directories(&directories_file, &model.lock()?.directory_history)?;
+    }
+    Ok(printed_output)
+}
+
+fn update(
+    model: &Arc<Mutex<Model>>,
+    event: event::Event,
+    clipboard: &mut Clipboard,
+) -> Result<(), VshellError> {
+    update::update(model, event, clipboard)?;
+    if let Some(notice) = clipboard.take_notice() {
+        model.lock()?.status = Some(Status::Info(notice.to_string()));
+    }
     Ok(())
 }
 
@@ -79,7 +230,7 @@ impl<'a> StringType<'a> {
     }
 }
 
-fn split_string(input: &str) -> Vec<StringType> {
+fn split_string(input: &str) -> Vec<StringType<'_>> {
     let mut result = Vec::new();
     let mut chars = input.char_indices().peekable();
     let mut last_index = 0;
@@ -131,14 +282,260 @@ fn split_string(input: &str) -> Vec<StringType> {
     result
 }
 
+/// Whether `candidate` contains `query`. Like in vim's smartcase, a lowercase query ignores
+/// case while a query with an uppercase letter has to match exactly.
+fn smart_case_matches(query: &str, candidate: &str) -> bool {
+    match query.chars().any(char::is_uppercase) {
+        true => candidate.contains(query),
+        false => candidate.to_lowercase().contains(query),
+    }
+}
+
+/// Like `smart_case_matches` but `candidate` has to start with `query`.
+fn smart_case_starts_with(query: &str, candidate: &str) -> bool {
+    match query.chars().any(char::is_uppercase) {
+        true => candidate.starts_with(query),
+        false => candidate.to_lowercase().starts_with(query),
+    }
+}
+
+/// Replaces the word that ends at `cursor` if it is an abbreviation. Returns the new input
+/// and cursor position.
+fn expand_abbreviation(
+    input: &str,
+    cursor: usize,
+    abbreviations: &BTreeMap<String, String>,
+) -> Option<(String, usize)> {
+    let before_cursor = input.get(..cursor)?;
+    let word_start = before_cursor
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    let expansion = abbreviations.get(&before_cursor[word_start..])?;
+    let expanded = format!("{}{}{}", &input[..word_start], expansion, &input[cursor..]);
+    Some((expanded, word_start + expansion.len()))
+}
+
+/// A short description of how long ago something happened, e.g. `2m ago`.
+fn format_relative_time(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..=9 => "now".to_string(),
+        10..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Formats `time` as UTC, e.g. `2024-01-31 13:05:09 UTC`.
+fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+    // converts days since the epoch to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Emulates how a terminal displays a lone `\r`: the text after it overwrites the start of
+/// the current line, so progress bars only show their final state.
+fn collapse_carriage_returns(input: &str) -> String {
+    if !input.contains('\r') {
+        return input.to_string();
+    }
+
+    let lines = input.split('\n').collect::<Vec<&str>>();
+    let mut result = String::with_capacity(input.len());
+    for (index, line) in lines.iter().enumerate() {
+        let is_last_line = index == lines.len() - 1;
+        // keep \r\n line endings intact
+        let (line, line_ending) = match line.strip_suffix('\r') {
+            Some(line) if !is_last_line => (line, "\r\n"),
+            _ => (*line, if is_last_line { "" } else { "\n" }),
+        };
+
+        let mut displayed_line: Vec<char> = Vec::new();
+        for segment in line.split('\r') {
+            for (position, c) in segment.chars().enumerate() {
+                if position < displayed_line.len() {
+                    displayed_line[position] = c;
+                } else {
+                    displayed_line.push(c);
+                }
+            }
+        }
+        result.extend(displayed_line);
+        result.push_str(line_ending);
+    }
+    result
+}
+
 #[derive(Debug, Default)]
 enum Mode {
     #[default]
     Idle,
     Command(String),
     Directory(Directory),
+    // the hint letters typed so far, the word is copied as soon as the hint is unambiguous
+    CopyWord(String),
+    // waits for `y` before running the action, any other key cancels it
+    Confirm(Confirmation),
+    // enter jumps to the next older match, see `:/`
+    Search(HistorySearch),
+    // enter jumps to the next word of the output that matches, see `:f`
+    Find(OutputSearch),
     Quit,
-    Executing(bool, u16, std::sync::mpsc::Sender<()>, JoinHandle<()>),
+    Executing(Execution),
+}
+
+#[derive(Debug)]
+struct Execution {
+    // the indicator in the output header bounces back and forth
+    forward: bool,
+    position: u16,
+    phase: Phase,
+    // what the running command has written so far
+    output: Option<Output>,
+    // the executing thread reports its phase and the output as it is written
+    progress: Receiver<Progress>,
+    cancel: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl Execution {
+    fn new(cancel: Sender<()>, progress: Receiver<Progress>, handle: JoinHandle<()>) -> Self {
+        Execution {
+            forward: true,
+            position: 0,
+            phase: Phase::Parsing,
+            output: None,
+            progress,
+            cancel,
+            handle,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Progress {
+    Phase(Phase),
+    Output(Output),
+}
+
+/// Where a command is in its life, shown while it executes so slow starts can be told
+/// apart from slow commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Parsing,
+    Spawning,
+    Running,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Parsing => "parsing",
+            Phase::Spawning => "spawning",
+            Phase::Running => "running",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HistorySearch {
+    query: String,
+    // index into the command history of the entry that is shown
+    matched: usize,
+}
+
+#[derive(Debug)]
+struct OutputSearch {
+    query: String,
+    // index of the matched word among the words of the shown output
+    matched: usize,
+}
+
+#[derive(Debug)]
+struct Confirmation {
+    message: String,
+    action: ConfirmedAction,
+}
+
+impl Confirmation {
+    fn dangerous(input: String) -> Self {
+        Confirmation {
+            message: format!("this may delete everything, really run `{}`? [y/N]", input),
+            action: ConfirmedAction::Execute(input),
+        }
+    }
+
+    fn clear_history(pins: bool) -> Self {
+        let message = match pins {
+            true => "clear the history and the pinned commands? [y/N]",
+            false => "clear the history? [y/N]",
+        };
+        Confirmation {
+            message: message.to_string(),
+            action: ConfirmedAction::ClearHistory(pins),
+        }
+    }
+
+    fn delete_history_entry(index: usize, input: &str) -> Self {
+        Confirmation {
+            message: format!("delete `{}` from the history? [y/N]", input),
+            action: ConfirmedAction::DeleteHistoryEntry(index),
+        }
+    }
+
+    fn pasted_commands(inputs: Vec<String>, dangerous: bool) -> Self {
+        let message = match dangerous {
+            true => format!(
+                "this may delete everything, really run {} pasted commands? [y/N]",
+                inputs.len()
+            ),
+            false => format!("run {} pasted commands? [y/N]", inputs.len()),
+        };
+        Confirmation {
+            message,
+            action: ConfirmedAction::ExecuteAll(inputs),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ConfirmedAction {
+    Execute(String),
+    // run one after the other, see `:pl`
+    ExecuteAll(Vec<String>),
+    // whether to remove the pinned commands as well
+    ClearHistory(bool),
+    // index into the command history
+    DeleteHistoryEntry(usize),
 }
 
 #[derive(Debug, PartialEq, Default)]
@@ -148,6 +545,17 @@ pub struct Directory {
     current_dir: PathBuf,
     children: Vec<File>,
     location: Option<Rect>,
+    // index into `children` of the entry highlighted with the arrow keys
+    selected: Option<usize>,
+    // read when the selection changes so the view does not touch the file system
+    preview: Option<Preview>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Preview {
+    Text(String),
+    Binary,
+    Unreadable(String),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -189,10 +597,216 @@ enum HintState {
     HideHints,
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq)]
 struct Config {
     hint_state: HintState,
     history_type: HistoryType,
+    // run commands in the background and keep the input editable while they execute
+    async_execution: bool,
+    // show the beginning of the highlighted file in the directory picker
+    picker_preview: bool,
+    directory_display: DirectoryDisplay,
+    show_hidden: bool,
+    // strip trailing newlines from pasted text so a paste never runs anything by itself
+    paste_guard: bool,
+    // typing one of the keys followed by a space replaces it with its expansion, see `:ab`
+    abbreviations: BTreeMap<String, String>,
+    // show how long ago each command in the history finished
+    show_timestamps: bool,
+    // cut long history entries off at the edge of the pane instead of wrapping them
+    truncate_history: bool,
+    // show the output of a running command as it is written instead of once it exits
+    stream_output: bool,
+    // ask before `:clh` and `:dh` remove anything
+    confirm_destructive: bool,
+    // used by `:se` when no shell is given
+    shell: String,
+    // warn at startup if `shell` can't be found
+    check_shell: bool,
+    // show stderr in a pane of its own below stdout
+    split_output: bool,
+    // show the number each command got when it was run next to its position in the history
+    history_numbers: bool,
+    // quit after this long without input, see `--idle-exit`
+    idle_exit: Option<Duration>,
+    theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            hint_state: HintState::default(),
+            history_type: HistoryType::default(),
+            async_execution: false,
+            picker_preview: false,
+            directory_display: DirectoryDisplay::default(),
+            show_hidden: false,
+            paste_guard: true,
+            abbreviations: BTreeMap::new(),
+            show_timestamps: true,
+            truncate_history: true,
+            stream_output: false,
+            confirm_destructive: false,
+            shell: String::from("sh"),
+            check_shell: true,
+            split_output: false,
+            history_numbers: false,
+            idle_exit: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Sets the field named `key`, used by `:set`.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn parse_bool(value: &str) -> Result<bool, String> {
+            match value {
+                "true" | "on" | "yes" | "1" => Ok(true),
+                "false" | "off" | "no" | "0" => Ok(false),
+                _ => Err(format!("expected on or off but got {}", value)),
+            }
+        }
+
+        match key {
+            "hint_state" => {
+                self.hint_state = match value {
+                    "show" => HintState::ShowHints,
+                    "hide" => HintState::HideHints,
+                    _ => return Err(format!("expected show or hide but got {}", value)),
+                }
+            }
+            "history_type" => {
+                self.history_type = match value {
+                    "commands" => HistoryType::CommandHistory,
+                    "directories" => HistoryType::DirectoryHistory,
+                    _ => {
+                        return Err(format!(
+                            "expected commands or directories but got {}",
+                            value
+                        ))
+                    }
+                }
+            }
+            "directory_display" => {
+                self.directory_display = match value {
+                    "absolute" => DirectoryDisplay::Absolute,
+                    "home" => DirectoryDisplay::Home,
+                    "relative" => DirectoryDisplay::CommonAncestor,
+                    _ => {
+                        return Err(format!(
+                            "expected absolute, home or relative but got {}",
+                            value
+                        ))
+                    }
+                }
+            }
+            "async_execution" => self.async_execution = parse_bool(value)?,
+            "picker_preview" => self.picker_preview = parse_bool(value)?,
+            "show_hidden" => self.show_hidden = parse_bool(value)?,
+            "paste_guard" => self.paste_guard = parse_bool(value)?,
+            "show_timestamps" => self.show_timestamps = parse_bool(value)?,
+            "truncate_history" => self.truncate_history = parse_bool(value)?,
+            "stream_output" => self.stream_output = parse_bool(value)?,
+            "confirm_destructive" => self.confirm_destructive = parse_bool(value)?,
+            "check_shell" => self.check_shell = parse_bool(value)?,
+            "split_output" => self.split_output = parse_bool(value)?,
+            "history_numbers" => self.history_numbers = parse_bool(value)?,
+            "shell" if !value.is_empty() => self.shell = value.to_string(),
+            "shell" => return Err("expected a shell".to_string()),
+            "idle_exit" => {
+                self.idle_exit = match value {
+                    "off" | "0" => None,
+                    _ => match value.parse::<f64>() {
+                        Ok(minutes) if minutes.is_finite() && minutes > 0.0 => {
+                            Some(Duration::from_secs_f64(minutes * 60.0))
+                        }
+                        _ => return Err(format!("expected minutes or off but got {}", value)),
+                    },
+                }
+            }
+            "theme" => {
+                self.theme = match value {
+                    "default" => Theme::default(),
+                    "mono" => Theme::monochrome(),
+                    _ => return Err(format!("expected default or mono but got {}", value)),
+                }
+            }
+            _ if key.starts_with("theme.") => self.theme.set(&key["theme.".len()..], value)?,
+            _ => return Err(format!("unknown setting {}", key)),
+        }
+        Ok(())
+    }
+}
+
+/// The colors vshell draws with, set with `theme` in the config file or `:set theme.<field>`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Theme {
+    foreground: Color,
+    background: Color,
+    error: Color,
+    // the letters in front of words in hint mode
+    hint: Color,
+    cursor: Color,
+    // the border of the focused pane
+    accent: Color,
+    // directories in the picker
+    directory: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            foreground: Color::White,
+            background: Color::Black,
+            error: Color::Red,
+            hint: Color::White,
+            cursor: Color::Green,
+            accent: Color::Cyan,
+            directory: Color::Green,
+        }
+    }
+}
+
+impl Theme {
+    /// Only shades of gray, for terminals with an unusual palette.
+    fn monochrome() -> Self {
+        Theme {
+            error: Color::White,
+            hint: Color::Gray,
+            cursor: Color::Gray,
+            accent: Color::White,
+            directory: Color::Gray,
+            ..Theme::default()
+        }
+    }
+
+    fn set(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let color = value
+            .parse::<Color>()
+            .map_err(|_| format!("expected a color but got {}", value))?;
+        match field {
+            "foreground" => self.foreground = color,
+            "background" => self.background = color,
+            "error" => self.error = color,
+            "hint" => self.hint = color,
+            "cursor" => self.cursor = color,
+            "accent" => self.accent = color,
+            "directory" => self.directory = color,
+            _ => return Err(format!("unknown theme color {}", field)),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+enum DirectoryDisplay {
+    #[default]
+    Absolute,
+    // replace the home directory with `~`
+    Home,
+    // strip the directory all entries have in common
+    CommonAncestor,
 }
 
 #[derive(Debug, PartialEq, Default)]
@@ -200,6 +814,8 @@ pub enum HistoryType {
     #[default]
     CommandHistory,
     DirectoryHistory,
+    // the background jobs that are still running
+    Jobs,
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -211,7 +827,7 @@ struct Output {
 impl fmt::Display for Output {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.output_type {
-            OutputType::Success(stdout, stderr) | OutputType::Error(stdout, stderr) => {
+            OutputType::Success(stdout, stderr, _) | OutputType::Error(stdout, stderr, _) => {
                 if stdout.is_empty() && stderr.is_empty() {
                     write!(f, "")
                 } else if stdout.is_empty() {
@@ -227,10 +843,30 @@ impl fmt::Display for Output {
     }
 }
 
+impl Output {
+    /// Applies `f` to stdout and stderr, keeping the origin and whether the command succeeded.
+    fn map_text(&self, f: impl Fn(&str) -> String) -> Output {
+        let output_type = match &self.output_type {
+            OutputType::Success(stdout, stderr, exit_code) => {
+                OutputType::Success(f(stdout), f(stderr), *exit_code)
+            }
+            OutputType::Error(stdout, stderr, exit_code) => {
+                OutputType::Error(f(stdout), f(stderr), *exit_code)
+            }
+            OutputType::Empty => OutputType::Empty,
+        };
+        Output {
+            origin: self.origin.clone(),
+            output_type,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 enum OutputType {
-    Success(String, String),
-    Error(String, String),
+    // stdout, stderr and the exit code, which is missing e.g. if a signal ended the command
+    Success(String, String, Option<i32>),
+    Error(String, String, Option<i32>),
     #[default]
     Empty,
 }
@@ -251,6 +887,26 @@ impl fmt::Display for Origin {
     }
 }
 
+/// A command that runs in the background, see `&`.
+#[derive(Debug)]
+struct Job {
+    number: usize,
+    input: String,
+    started: Instant,
+    cancel: Sender<()>,
+}
+
+/// The candidates listed by Tab, pressing it again cycles through them.
+#[derive(Debug)]
+struct Completion {
+    candidates: Vec<String>,
+    // the candidate currently inserted, none while only the common prefix is
+    selected: Option<usize>,
+    // where the completed word starts in the input
+    start: usize,
+    output: Output,
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 struct CommandWithoutOutput {
     cursor_position: u64,
@@ -261,6 +917,22 @@ struct CommandWithoutOutput {
 struct CompletedCommand {
     input: String,
     output: Output,
+    duration: Option<Duration>,
+    // where the output was scrolled to when the entry was last shown
+    output_scroll: u16,
+    // hidden entries are left out of the history list unless `show_hidden` is set
+    hidden: bool,
+    // unknown for commands loaded from the history file
+    finished_at: Option<SystemTime>,
+    // the working directory the command was run in, also unknown for loaded commands
+    directory: Option<PathBuf>,
+    // the number of the background job while the command is still running, see `&`
+    job: Option<usize>,
+    // whether the output differs from the previous run of the same input, unknown for the
+    // first run and for loaded commands
+    changed: Option<bool>,
+    // counts up in the order the commands were run, from 1, see `:hn`
+    number: usize,
 }
 
 impl CompletedCommand {
@@ -278,16 +950,26 @@ impl CompletedCommand {
                             Output {
                                 origin,
                                 output_type: OutputType::Success(
-                                    String::from_utf8_lossy(&executed_command.stdout).to_string(),
-                                    String::from_utf8_lossy(&executed_command.stderr).to_string(),
+                                    collapse_carriage_returns(&String::from_utf8_lossy(
+                                        &executed_command.stdout,
+                                    )),
+                                    collapse_carriage_returns(&String::from_utf8_lossy(
+                                        &executed_command.stderr,
+                                    )),
+                                    executed_command.status.code(),
                                 ),
                             }
                         } else {
                             Output {
                                 origin,
                                 output_type: OutputType::Error(
-                                    String::from_utf8_lossy(&executed_command.stdout).to_string(),
-                                    String::from_utf8_lossy(&executed_command.stderr).to_string(),
+                                    collapse_carriage_returns(&String::from_utf8_lossy(
+                                        &executed_command.stdout,
+                                    )),
+                                    collapse_carriage_returns(&String::from_utf8_lossy(
+                                        &executed_command.stderr,
+                                    )),
+                                    executed_command.status.code(),
                                 ),
                             }
                         }
@@ -299,6 +981,7 @@ impl CompletedCommand {
                                 output_type: OutputType::Error(
                                     "".to_string(),
                                     format!("Command not found: {}", input),
+                                    Some(127),
                                 ),
                             }
                         } else {
@@ -307,12 +990,14 @@ impl CompletedCommand {
                                 output_type: OutputType::Error(
                                     "".to_string(),
                                     executed_command.to_string(),
+                                    None,
                                 ),
                             }
                         }
                     }
                 }
             },
+            ..Default::default()
         }
     }
 }
@@ -348,6 +1033,13 @@ impl Default for CurrentView {
     }
 }
 
+/// An entry of the history pane, addressed by the number shown next to it.
+#[derive(Debug, PartialEq)]
+enum HistoryEntry {
+    Pinned(usize),
+    Command(usize),
+}
+
 #[derive(Debug, Default)]
 struct Model {
     mode: Mode,
@@ -357,6 +1049,98 @@ struct Model {
     directory_history: Vec<PathBuf>,
     pinned_commands: Vec<CommandWithoutOutput>,
     current_command: CurrentView,
+    // the last editing command (`:c`, `:rs`, `:rg`) so `:.` can repeat it
+    last_edit: Option<String>,
+    output_scroll: OutputScroll,
+    // a short notice shown at the bottom until the next key press
+    status: Option<Status>,
+    // every completed command is appended to this transcript while recording
+    recording: Option<std::fs::File>,
+    // named commands with `{}` placeholders, see `:td` and `:tpl`
+    templates: BTreeMap<String, String>,
+    // the inner size of the output pane, set by the view every frame
+    output_size: PaneSize,
+    // where the directory picker was when it was closed, `:cp!` opens it there again
+    last_picker_directory: Option<PathBuf>,
+    // completed commands are appended here so the history survives restarts
+    history_file: Option<PathBuf>,
+    // show the current output as aligned columns, reset when another output is shown
+    table_view: bool,
+    // only list the commands that were run in the current directory, see `:here`
+    history_here: bool,
+    // the number of the next command that is run in the background
+    next_job: usize,
+    jobs: Vec<Job>,
+    // outputs kept under a name with `:buf:save`
+    buffers: BTreeMap<String, Output>,
+    // cleared by any key other than Tab
+    completion: Option<Completion>,
+    // set with `:alias`, the first word of a command is replaced by its alias
+    aliases: BTreeMap<String, String>,
+    // the text ctrl+k, ctrl+u or ctrl+w removed last, ctrl+y inserts it again
+    kill_buffer: String,
+    // the number the last command that was added to the history got
+    last_command_number: usize,
+}
+
+/// Passed to commands as `COLUMNS` and `LINES` so width-aware tools format their output to
+/// fit the output pane even though they do not write to a terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PaneSize {
+    columns: u16,
+    lines: u16,
+}
+
+#[derive(Debug, PartialEq)]
+enum Status {
+    Info(String),
+    Error(String),
+}
+
+#[derive(Debug, Default)]
+struct OutputScroll {
+    // the first line of the output that is visible
+    offset: u16,
+    // set by the view every frame so scrolling can be clamped
+    total_lines: u16,
+    visible_lines: u16,
+    // the user scrolled up, so output of a running command no longer scrolls to the bottom
+    detached: bool,
+}
+
+impl OutputScroll {
+    fn max_offset(&self) -> u16 {
+        self.total_lines.saturating_sub(self.visible_lines)
+    }
+
+    /// Starts at the top of new output and follows it if it is still being written.
+    fn reset(&mut self) {
+        self.offset = 0;
+        self.detached = false;
+    }
+
+    fn page_up(&mut self) {
+        self.offset = self.offset.saturating_sub(self.visible_lines.max(1));
+        self.detached = true;
+    }
+
+    fn page_down(&mut self) {
+        self.offset = self
+            .offset
+            .saturating_add(self.visible_lines.max(1))
+            .min(self.max_offset());
+        self.detached = self.offset < self.max_offset();
+    }
+
+    fn top(&mut self) {
+        self.offset = 0;
+        self.detached = true;
+    }
+
+    fn bottom(&mut self) {
+        self.offset = self.max_offset();
+        self.detached = false;
+    }
 }
 
 impl Model {
@@ -364,12 +1148,375 @@ impl Model {
         matches!(self.mode, Mode::Quit)
     }
 
+    /// Whether the last input was longer ago than `idle_exit` allows. Running commands, also
+    /// those in the background, keep the session alive.
+    fn is_idle_for_too_long(&self, idle: Duration) -> bool {
+        self.config.idle_exit.is_some_and(|limit| idle >= limit) && !self.is_running()
+    }
+
+    /// Whether a command is running, in the foreground or in the background.
+    fn is_running(&self) -> bool {
+        matches!(self.mode, Mode::Executing(_)) || !self.jobs.is_empty()
+    }
+
     fn set_current_view_from_command(&mut self, cursor_position: u64, command: String) {
+        self.remember_output_scroll();
         self.current_command = CurrentView::CommandWithoutOutput(CommandWithoutOutput {
             cursor_position,
             input: command,
         });
         self.command_history_index = self.command_history.len();
+        self.output_scroll.reset();
+        self.table_view = false;
+    }
+
+    /// Puts the input of the command that was just cancelled back into the input pane so it can
+    /// be fixed and run again. Input that was typed while the command ran is kept instead.
+    fn restore_cancelled_input(&mut self) {
+        if matches!(&self.current_command, CurrentView::CommandWithoutOutput(command) if !command.input.is_empty())
+        {
+            return;
+        }
+        if let Some(input) = self
+            .command_history
+            .last()
+            .map(|completed_command| completed_command.input.clone())
+        {
+            self.set_current_view_from_command(input.len() as u64, input);
+        }
+    }
+
+    fn remember_output_scroll(&mut self) {
+        if let CurrentView::CommandWithOutput(_) = self.current_command {
+            if let Some(shown) = self.command_history.get_mut(self.command_history_index) {
+                shown.output_scroll = self.output_scroll.offset;
+            }
+        }
+    }
+
+    /// Whether the history lists the command at `index`. `current_directory` is read once by
+    /// the caller since this is checked for every entry.
+    fn is_listed(&self, index: usize, current_directory: Option<&Path>) -> bool {
+        let command = &self.command_history[index];
+        (self.config.show_hidden || !command.hidden)
+            && (!self.history_here
+                || command.directory.is_some() && command.directory.as_deref() == current_directory)
+    }
+
+    /// Shows the history entry at `index` where its output was last scrolled to.
+    fn show_history_entry(&mut self, index: usize) {
+        self.remember_output_scroll();
+        self.command_history_index = index;
+        let completed_command = &self.command_history[index];
+        self.output_scroll.offset = completed_command.output_scroll;
+        self.output_scroll.detached = false;
+        self.table_view = false;
+        self.current_command = CurrentView::CommandWithOutput(completed_command.clone());
+    }
+
+    /// Forgets all commands, the history file included.
+    fn clear_history(&mut self, pins: bool) {
+        self.command_history.clear();
+        self.command_history_index = 0;
+        self.current_command = CurrentView::CommandWithoutOutput(CommandWithoutOutput::default());
+        self.output_scroll.reset();
+        if pins {
+            self.pinned_commands.clear();
+        }
+        if let Some(history_file) = &self.history_file {
+            if let Err(e) = history::clear(history_file) {
+                self.status = Some(Status::Error(format!(
+                    "could not clear the history file: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    /// Removes the command at `index` from the history, the history file included. A deleted
+    /// entry that was shown is replaced by an empty input.
+    fn delete_history_entry(&mut self, index: usize) {
+        let shown = index == self.command_history_index
+            && matches!(self.current_command, CurrentView::CommandWithOutput(_));
+        self.command_history.remove(index);
+        if shown {
+            self.current_command =
+                CurrentView::CommandWithoutOutput(CommandWithoutOutput::default());
+            self.command_history_index = self.command_history.len();
+            self.output_scroll.reset();
+        } else if index <= self.command_history_index && self.command_history_index > 0 {
+            self.command_history_index -= 1;
+        }
+        if let Some(history_file) = &self.history_file {
+            if let Err(e) = history::save(history_file, &self.command_history) {
+                self.status = Some(Status::Error(format!("history is not saved: {}", e)));
+            }
+        }
+    }
+
+    /// Keeps only the most recent run of each input, the history file included. Returns how
+    /// many entries were removed.
+    fn dedupe_history(&mut self) -> usize {
+        let shown = self
+            .command_history
+            .get(self.command_history_index)
+            .map(|command| command.input.clone());
+        let before = self.command_history.len();
+        let mut seen = HashSet::new();
+        // walking from the newest entry keeps the most recent run
+        let mut kept = std::mem::take(&mut self.command_history)
+            .into_iter()
+            .rev()
+            .filter(|command| seen.insert(command.input.clone()))
+            .collect::<Vec<CompletedCommand>>();
+        kept.reverse();
+        self.command_history = kept;
+        // an entry that was shown is replaced by the run that was kept
+        self.command_history_index = shown
+            .and_then(|input| {
+                self.command_history
+                    .iter()
+                    .position(|command| command.input == input)
+            })
+            .unwrap_or(self.command_history.len());
+        if let Some(history_file) = &self.history_file {
+            if let Err(e) = history::save(history_file, &self.command_history) {
+                self.status = Some(Status::Error(format!("history is not saved: {}", e)));
+            }
+        }
+        before - self.command_history.len()
+    }
+
+    /// Expands the abbreviation in front of the cursor, called before a space is inserted.
+    fn expand_abbreviation(&mut self) {
+        let Model {
+            config,
+            current_command,
+            ..
+        } = self;
+        if let CurrentView::CommandWithoutOutput(command) = current_command {
+            if let Some((input, cursor)) = expand_abbreviation(
+                &command.input,
+                command.cursor_position as usize,
+                &config.abbreviations,
+            ) {
+                command.input = input;
+                command.cursor_position = cursor as u64;
+            }
+        }
+    }
+
+    /// The newest command before `before` whose input matches `query`, see
+    /// `smart_case_matches`. Wraps around to the newest command after the oldest one was
+    /// reached.
+    fn search_history(&self, query: &str, before: usize) -> Option<usize> {
+        let matches =
+            |index: &usize| smart_case_matches(query, &self.command_history[*index].input);
+        let before = before.min(self.command_history.len());
+        (0..before)
+            .rev()
+            .find(matches)
+            .or_else(|| (before..self.command_history.len()).rev().find(matches))
+    }
+
+    /// Which of the matches of `query` the entry `matched` is, counting from the newest like
+    /// enter goes through them, and how many entries match.
+    fn history_match_count(&self, query: &str, matched: usize) -> (usize, usize) {
+        let matches = self
+            .command_history
+            .iter()
+            .enumerate()
+            .filter(|(_, command)| smart_case_matches(query, &command.input))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        let position = matches.iter().filter(|index| **index >= matched).count();
+        (position, matches.len())
+    }
+
+    /// Like `history_match_count` for the words of the shown output, counting from the top.
+    fn output_match_count(&self, query: &str, matched: usize) -> (usize, usize) {
+        let Some(output) = self.output_string() else {
+            return (0, 0);
+        };
+        let matches = split_string(&output)
+            .into_iter()
+            .filter(|element| matches!(element, StringType::Word(_)))
+            .enumerate()
+            .filter(|(_, word)| smart_case_matches(query, word.as_str()))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        let position = matches.iter().filter(|index| **index <= matched).count();
+        (position, matches.len())
+    }
+
+    /// The first word of the shown output at or after the word with index `from` that matches
+    /// `query`, wrapping around to the first word.
+    fn find_in_output(&self, query: &str, from: usize) -> Option<usize> {
+        let output = self.output_string()?;
+        let words = split_string(&output)
+            .into_iter()
+            .filter_map(|element| match element {
+                StringType::Word(word) => Some(word),
+                _ => None,
+            })
+            .collect::<Vec<&str>>();
+        let matches = |index: &usize| smart_case_matches(query, words[*index]);
+        let from = from.min(words.len());
+        (from..words.len())
+            .find(matches)
+            .or_else(|| (0..from).find(matches))
+    }
+
+    /// The shown output as it appears on screen, without color codes.
+    fn output_string(&self) -> Option<String> {
+        match &self.current_command {
+            CurrentView::CommandWithoutOutput(_) => None,
+            CurrentView::CommandWithOutput(command) => {
+                Some(ansi::strip(&command.output.to_string()))
+            }
+            CurrentView::Output(output) => Some(ansi::strip(&output.to_string())),
+        }
+    }
+
+    /// Pinned commands are numbered first, followed by the command history from newest to oldest.
+    fn history_entry(&self, number: usize) -> Option<HistoryEntry> {
+        if number < self.pinned_commands.len() {
+            Some(HistoryEntry::Pinned(number))
+        } else if number < self.pinned_commands.len() + self.command_history.len() {
+            Some(HistoryEntry::Command(
+                self.command_history.len() + self.pinned_commands.len() - number - 1,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn history_input(&self, number: usize) -> Option<&str> {
+        match self.history_entry(number)? {
+            HistoryEntry::Pinned(index) => Some(self.pinned_commands[index].input.as_str()),
+            HistoryEntry::Command(index) => Some(self.command_history[index].input.as_str()),
+        }
+    }
+
+    fn record(&mut self, completed_command: &CompletedCommand) {
+        if let Some(recording) = &mut self.recording {
+            let entry = format!(
+                "[{}] $ {}\n{}\n",
+                format_timestamp(SystemTime::now()),
+                completed_command.input,
+                completed_command.output
+            );
+            if let Err(e) = recording.write_all(entry.as_bytes()) {
+                self.recording = None;
+                self.status = Some(Status::Error(format!("recording stopped: {}", e)));
+            }
+        }
+    }
+
+    fn save_to_history_file(&mut self, completed_command: &CompletedCommand) {
+        if let Some(history_file) = &self.history_file {
+            if let Err(e) = history::append(history_file, completed_command) {
+                self.history_file = None;
+                self.status = Some(Status::Error(format!("history is not saved: {}", e)));
+            }
+        }
+    }
+
+    /// Whether new output may replace the current view. With async execution the user may be
+    /// typing or browsing the history while a command runs.
+    fn output_replaces_current_view(&self) -> bool {
+        !self.config.async_execution
+            || match &self.current_command {
+                CurrentView::CommandWithoutOutput(command) => command.input.is_empty(),
+                CurrentView::CommandWithOutput(_) => false,
+                CurrentView::Output(_) => true,
+            }
+    }
+
+    /// Adds a finished command to the history and shows its output unless the user is busy
+    /// with something else. A mode the user entered in the meantime is kept.
+    fn complete_command(&mut self, completed_command: CompletedCommand) {
+        self.add_completed_command(completed_command);
+        if matches!(self.mode, Mode::Executing(_)) {
+            self.mode = Mode::Idle;
+        }
+    }
+
+    /// Like `complete_command` but stays in the current mode, for commands that are part of
+    /// a sequence that is still running.
+    fn add_completed_command(&mut self, mut completed_command: CompletedCommand) {
+        completed_command.finished_at = Some(SystemTime::now());
+        if completed_command.directory.is_none() {
+            completed_command.directory = std::env::current_dir().ok();
+        }
+        let keep_current_view = !self.output_replaces_current_view();
+        let was_at_end = self.command_history_index == self.command_history.len();
+        completed_command.changed = self
+            .command_history
+            .iter()
+            .rev()
+            .find(|previous| previous.input == completed_command.input && previous.job.is_none())
+            .map(|previous| previous.output.output_type != completed_command.output.output_type);
+
+        self.record(&completed_command);
+        self.save_to_history_file(&completed_command);
+        let output = completed_command.output.clone();
+        self.push_to_history(completed_command);
+        if !keep_current_view {
+            self.current_command = CurrentView::Output(output);
+            self.command_history_index = self.command_history.len();
+            self.output_scroll.reset();
+            self.table_view = false;
+        } else if was_at_end {
+            self.command_history_index = self.command_history.len();
+        }
+        let _ = self.add_current_directory_to_history();
+    }
+
+    /// Appends `completed_command` to the history and gives it the next command number.
+    fn push_to_history(&mut self, mut completed_command: CompletedCommand) {
+        self.last_command_number += 1;
+        completed_command.number = self.last_command_number;
+        self.command_history.push(completed_command);
+    }
+
+    /// The index into the history of the command with the number `number`.
+    fn history_index_of_number(&self, number: usize) -> Option<usize> {
+        self.command_history
+            .iter()
+            .position(|command| command.number == number)
+    }
+
+    /// Puts the output of a finished background job into its history entry. The entry may
+    /// have been deleted in the meantime.
+    fn finish_background_job(&mut self, job: usize, completed_command: CompletedCommand) {
+        self.jobs.retain(|running| running.number != job);
+        let Some(index) = self
+            .command_history
+            .iter()
+            .position(|command| command.job == Some(job))
+        else {
+            return;
+        };
+        let entry = &mut self.command_history[index];
+        entry.output = completed_command.output;
+        entry.duration = completed_command.duration;
+        entry.finished_at = Some(SystemTime::now());
+        entry.job = None;
+        if self.command_history_index == index {
+            if let CurrentView::CommandWithOutput(shown) = &mut self.current_command {
+                shown.output = entry.output.clone();
+            }
+        }
+        self.status = Some(Status::Info(format!("[{}] done: {}", job, entry.input)));
+    }
+
+    /// The most recently visited directory that is not `current_directory`.
+    fn last_directory(&self, current_directory: &Path) -> Option<&PathBuf> {
+        self.directory_history
+            .iter()
+            .rev()
+            .find(|directory| directory.as_path() != current_directory)
     }
 
     fn add_current_directory_to_history(&mut self) -> Result<(), std::io::Error> {
@@ -390,6 +1537,466 @@ impl Model {
 mod test {
     use super::*;
 
+    #[test]
+    fn delete_history_entry() {
+        let mut model = Model::default();
+        for input in ["ls", "pwd", "echo"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        model.command_history_index = 3;
+        model.delete_history_entry(1);
+        assert_eq!(model.command_history_index, 2);
+        assert_eq!(model.command_history[1].input, "echo");
+
+        model.command_history_index = 0;
+        model.delete_history_entry(1);
+        assert_eq!(model.command_history_index, 0);
+        assert_eq!(model.command_history.len(), 1);
+
+        // the entry on screen is deleted
+        model.command_history.push(CompletedCommand {
+            input: "date".to_string(),
+            ..Default::default()
+        });
+        model.show_history_entry(1);
+        model.delete_history_entry(1);
+        assert_eq!(model.command_history_index, 1);
+        assert_eq!(model.current_command.input_str(), Some(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_codes() {
+        let run = |input: &str, program: &str, args: &[&str]| {
+            let output = std::process::Command::new(program).args(args).output();
+            CompletedCommand::new(input.to_string(), output, Origin::Vshell)
+                .output
+                .output_type
+        };
+        assert_eq!(
+            run("echo a", "echo", &["a"]),
+            OutputType::Success("a\n".to_string(), String::new(), Some(0))
+        );
+        assert_eq!(
+            run("exit 3", "sh", &["-c", "echo oops >&2; exit 3"]),
+            OutputType::Error(String::new(), "oops\n".to_string(), Some(3))
+        );
+        assert!(matches!(
+            run("missing", "vshell_test_missing_program", &[]),
+            OutputType::Error(_, _, Some(127))
+        ));
+    }
+
+    #[test]
+    fn command_numbers() {
+        let mut model = Model::default();
+        for input in ["ls", "pwd", "echo"] {
+            model.push_to_history(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        model.delete_history_entry(1);
+        model.push_to_history(CompletedCommand {
+            input: "date".to_string(),
+            ..Default::default()
+        });
+        let numbers = model
+            .command_history
+            .iter()
+            .map(|command| command.number)
+            .collect::<Vec<usize>>();
+        // numbers stay with their command when others are removed
+        assert_eq!(numbers, [1, 3, 4]);
+        assert_eq!(model.history_index_of_number(3), Some(1));
+        assert_eq!(model.history_index_of_number(2), None);
+    }
+
+    #[test]
+    fn dedupe_history() {
+        let mut model = Model::default();
+        for input in ["ls", "pwd", "ls", "echo", "pwd"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        // showing the first `pwd`, which is removed
+        model.command_history_index = 1;
+        assert_eq!(model.dedupe_history(), 2);
+        let inputs = model
+            .command_history
+            .iter()
+            .map(|command| command.input.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(inputs, ["ls", "echo", "pwd"]);
+        assert_eq!(model.command_history_index, 2);
+
+        model.command_history_index = 3;
+        assert_eq!(model.dedupe_history(), 0);
+        assert_eq!(model.command_history_index, 3);
+    }
+
+    #[test]
+    fn changed_since_previous_run() {
+        let mut model = Model {
+            directory_history: vec![std::env::current_dir().unwrap()],
+            ..Default::default()
+        };
+        let run = |stdout: &str| CompletedCommand {
+            input: "date".to_string(),
+            output: Output {
+                output_type: OutputType::Success(stdout.to_string(), String::new(), Some(0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        model.add_completed_command(run("monday"));
+        model.add_completed_command(run("monday"));
+        model.add_completed_command(run("tuesday"));
+        let changed = model
+            .command_history
+            .iter()
+            .map(|command| command.changed)
+            .collect::<Vec<Option<bool>>>();
+        assert_eq!(changed, [None, Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn history_here() {
+        let mut model = Model::default();
+        for directory in [
+            std::env::current_dir().ok(),
+            Some(PathBuf::from("/elsewhere")),
+            None,
+        ] {
+            model.command_history.push(CompletedCommand {
+                input: "ls".to_string(),
+                directory,
+                ..Default::default()
+            });
+        }
+        let current_directory = std::env::current_dir().ok();
+        let current_directory = current_directory.as_deref();
+        assert!((0..3).all(|index| model.is_listed(index, current_directory)));
+        model.history_here = true;
+        assert!(model.is_listed(0, current_directory));
+        assert!(!model.is_listed(1, current_directory));
+        assert!(!model.is_listed(2, current_directory));
+    }
+
+    #[test]
+    fn finish_background_job() {
+        let mut model = Model::default();
+        for (input, job) in [("sleep 1 &", Some(0)), ("ls", None)] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                job,
+                ..Default::default()
+            });
+        }
+        let (cancel, _) = std::sync::mpsc::channel();
+        model.jobs.push(Job {
+            number: 0,
+            input: "sleep 1".to_string(),
+            started: Instant::now(),
+            cancel,
+        });
+        let output = Output {
+            output_type: OutputType::Success("done".to_string(), String::new(), Some(0)),
+            ..Default::default()
+        };
+        model.finish_background_job(
+            0,
+            CompletedCommand {
+                output: output.clone(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(model.command_history[0].output, output);
+        assert_eq!(model.command_history[0].job, None);
+        assert!(model.jobs.is_empty());
+        assert_eq!(model.command_history[1].output, Output::default());
+
+        // finishing a job that was deleted from the history changes nothing
+        model.finish_background_job(1, CompletedCommand::default());
+        assert_eq!(model.command_history[0].output, output);
+    }
+
+    #[test]
+    fn relative_time() {
+        assert_eq!(format_relative_time(Duration::from_secs(3)), "now");
+        assert_eq!(format_relative_time(Duration::from_secs(42)), "42s ago");
+        assert_eq!(format_relative_time(Duration::from_secs(150)), "2m ago");
+        assert_eq!(format_relative_time(Duration::from_secs(7200)), "2h ago");
+        assert_eq!(format_relative_time(Duration::from_secs(200_000)), "2d ago");
+    }
+
+    #[test]
+    fn abbreviations() {
+        let abbreviations = BTreeMap::from([("gco".to_string(), "git checkout".to_string())]);
+        assert_eq!(
+            expand_abbreviation("gco", 3, &abbreviations),
+            Some(("git checkout".to_string(), 12))
+        );
+        assert_eq!(
+            expand_abbreviation("sudo gco main", 8, &abbreviations),
+            Some(("sudo git checkout main".to_string(), 17))
+        );
+        assert_eq!(expand_abbreviation("xgco", 4, &abbreviations), None);
+        assert_eq!(expand_abbreviation("gco", 2, &abbreviations), None);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(smart_case_matches("cargo", "Cargo.toml"));
+        assert!(smart_case_matches("Cargo", "Cargo.toml"));
+        assert!(!smart_case_matches("Cargo", "cargo build"));
+        assert!(smart_case_starts_with("src", "Src"));
+        assert!(!smart_case_starts_with("Src", "src"));
+        assert!(!smart_case_starts_with("rc", "src"));
+    }
+
+    #[test]
+    fn search_history() {
+        let mut model = Model::default();
+        for input in ["cargo build", "ls", "Cargo test", "echo"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        let newest = model.command_history.len();
+        assert_eq!(model.search_history("cargo", newest), Some(2));
+        assert_eq!(model.search_history("Cargo", newest), Some(2));
+        assert_eq!(model.search_history("Cargo", 2), Some(2));
+        assert_eq!(model.search_history("cargo", 2), Some(0));
+        // wraps around
+        assert_eq!(model.search_history("cargo", 0), Some(2));
+        assert_eq!(model.search_history("ls", 1), Some(1));
+        assert_eq!(model.search_history("rustc", newest), None);
+        assert_eq!(model.history_match_count("cargo", 2), (1, 2));
+        assert_eq!(model.history_match_count("cargo", 0), (2, 2));
+        assert_eq!(model.history_match_count("rustc", 0), (0, 0));
+    }
+
+    #[test]
+    fn restore_cancelled_input() {
+        let mut model = Model::default();
+        model.command_history.push(CompletedCommand {
+            input: "sleep 100".to_string(),
+            ..Default::default()
+        });
+        model.current_command = CurrentView::Output(Output::default());
+        model.restore_cancelled_input();
+        assert_eq!(model.current_command.input_str(), Some("sleep 100"));
+        assert_eq!(model.current_command.cursor_position(), Some(9));
+
+        // with async execution the next command may already be typed
+        model.set_current_view_from_command(2, "ls".to_string());
+        model.restore_cancelled_input();
+        assert_eq!(model.current_command.input_str(), Some("ls"));
+    }
+
+    #[test]
+    fn complete_command_keeps_mode() {
+        let mut model = Model {
+            directory_history: vec![std::env::current_dir().unwrap()],
+            ..Default::default()
+        };
+        let (cancel, _) = std::sync::mpsc::channel();
+        let (_, progress) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(|| {});
+        model.mode = Mode::Executing(Execution::new(cancel, progress, handle));
+        model.complete_command(CompletedCommand::default());
+        assert!(matches!(model.mode, Mode::Idle));
+
+        // e.g. `:` was pressed and the command was cancelled before it finished
+        model.mode = Mode::Command("q".to_string());
+        model.complete_command(CompletedCommand::default());
+        assert!(matches!(&model.mode, Mode::Command(command) if command == "q"));
+    }
+
+    #[test]
+    fn find_in_output() {
+        let mut model = Model::default();
+        assert_eq!(model.find_in_output("src", 0), None);
+        model.current_command = CurrentView::Output(Output {
+            output_type: OutputType::Success(
+                "Cargo.toml\nsrc\ttarget\nsrc/main.rs".to_string(),
+                String::new(),
+                Some(0),
+            ),
+            ..Default::default()
+        });
+        assert_eq!(model.find_in_output("src", 0), Some(1));
+        assert_eq!(model.find_in_output("src", 2), Some(3));
+        // wraps around
+        assert_eq!(model.find_in_output("src", 4), Some(1));
+        assert_eq!(model.find_in_output("cargo", 0), Some(0));
+        assert_eq!(model.find_in_output("CARGO", 0), None);
+        assert_eq!(model.find_in_output("lib", 0), None);
+        assert_eq!(model.output_match_count("src", 3), (2, 2));
+        assert_eq!(model.output_match_count("cargo", 0), (1, 1));
+    }
+
+    #[test]
+    fn last_directory() {
+        let mut model = Model {
+            directory_history: vec![PathBuf::from("/tmp")],
+            ..Default::default()
+        };
+        assert_eq!(model.last_directory(Path::new("/tmp")), None);
+
+        model.directory_history.push(PathBuf::from("/home"));
+        model.directory_history.push(PathBuf::from("/tmp"));
+        assert_eq!(
+            model.last_directory(Path::new("/tmp")),
+            Some(&PathBuf::from("/home"))
+        );
+        assert_eq!(
+            model.last_directory(Path::new("/home")),
+            Some(&PathBuf::from("/tmp"))
+        );
+    }
+
+    #[test]
+    fn history_entries_remember_their_scroll() {
+        let mut model = Model::default();
+        for input in ["first", "second"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        model.show_history_entry(1);
+        model.output_scroll.offset = 7;
+        model.show_history_entry(0);
+        assert_eq!(model.output_scroll.offset, 0);
+        model.show_history_entry(1);
+        assert_eq!(model.output_scroll.offset, 7);
+    }
+
+    #[test]
+    fn output_scroll_stays_in_bounds() {
+        let mut scroll = OutputScroll {
+            offset: 0,
+            total_lines: 25,
+            visible_lines: 10,
+            ..Default::default()
+        };
+        scroll.page_down();
+        assert_eq!(scroll.offset, 10);
+        scroll.page_down();
+        assert_eq!(scroll.offset, 15);
+        scroll.page_up();
+        assert_eq!(scroll.offset, 5);
+        scroll.bottom();
+        assert_eq!(scroll.offset, 15);
+        scroll.top();
+        assert_eq!(scroll.offset, 0);
+        assert!(scroll.detached);
+        scroll.page_down();
+        scroll.page_down();
+        assert!(!scroll.detached);
+    }
+
+    #[test]
+    fn set_config() {
+        let mut config = Config::default();
+        assert_eq!(config.set("paste_guard", "off"), Ok(()));
+        assert!(!config.paste_guard);
+        assert_eq!(config.set("directory_display", "home"), Ok(()));
+        assert_eq!(config.directory_display, DirectoryDisplay::Home);
+        assert!(config.set("async_execution", "maybe").is_err());
+        assert!(config.set("unknown", "on").is_err());
+        assert_eq!(config.set("idle_exit", "1.5"), Ok(()));
+        assert_eq!(config.idle_exit, Some(Duration::from_secs(90)));
+        assert!(config.set("idle_exit", "-1").is_err());
+        assert_eq!(config.set("theme.cursor", "#ff8800"), Ok(()));
+        assert_eq!(config.theme.cursor, Color::Rgb(255, 136, 0));
+        assert!(config.set("theme.cursor", "sparkly").is_err());
+        assert!(config.set("theme.border", "red").is_err());
+    }
+
+    #[test]
+    fn idle_exit() {
+        let mut model = Model::default();
+        assert!(!model.is_idle_for_too_long(Duration::from_secs(3600)));
+        model.config.idle_exit = Some(Duration::from_secs(60));
+        assert!(!model.is_idle_for_too_long(Duration::from_secs(59)));
+        assert!(model.is_idle_for_too_long(Duration::from_secs(60)));
+
+        let (cancel, _) = std::sync::mpsc::channel();
+        model.jobs.push(Job {
+            number: 0,
+            input: "sleep 100".to_string(),
+            started: Instant::now(),
+            cancel,
+        });
+        assert!(!model.is_idle_for_too_long(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn command_line_settings() {
+        let args = ["--idle-exit", "10", "--hint-state", "show"].map(String::from);
+        let settings = parse_arguments(args.into_iter()).unwrap().settings;
+        assert_eq!(settings[0], ("idle_exit".to_string(), "10".to_string()));
+        assert_eq!(settings.len(), 2);
+        for args in [&["--idle-exit"][..], &["--idle-exit", "soon"], &["10"]] {
+            let args = args.iter().map(|arg| arg.to_string());
+            assert!(parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn command_argument() {
+        let args = ["-c", "ls -la", "--hint-state", "show"].map(String::from);
+        let arguments = parse_arguments(args.into_iter()).unwrap();
+        assert_eq!(arguments.command.as_deref(), Some("ls -la"));
+        assert!(!arguments.exit_after_command);
+        assert_eq!(arguments.settings.len(), 1);
+
+        let args = ["-C", "date"].map(String::from);
+        let arguments = parse_arguments(args.into_iter()).unwrap();
+        assert_eq!(arguments.command.as_deref(), Some("date"));
+        assert!(arguments.exit_after_command);
+
+        for args in [&["-c"][..], &["-c", "ls", "-C", "pwd"]] {
+            let args = args.iter().map(|arg| arg.to_string());
+            assert!(parse_arguments(args).is_err());
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(951_825_845)),
+            "2000-02-29 12:04:05 UTC"
+        );
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_704_067_199)),
+            "2023-12-31 23:59:59 UTC"
+        );
+    }
+
+    #[test]
+    fn collapse_progress_bar() {
+        assert_eq!(
+            collapse_carriage_returns("downloading\n 10%\r 50%\r100%\ndone"),
+            "downloading\n100%\ndone"
+        );
+        assert_eq!(collapse_carriage_returns("abcdef\rxy"), "xycdef");
+        assert_eq!(
+            collapse_carriage_returns("windows\r\nline endings\r\n"),
+            "windows\r\nline endings\r\n"
+        );
+    }
+
     #[test]
     fn sort_files() {
         let mut files = vec![
@@ -4,11 +4,18 @@ use crossterm::{
 };
 use ratatui::prelude::*;
 use std::{
+    any::Any,
+    cell::Cell,
     io::{stdout, Write},
     panic,
 };
 
-pub(crate) fn init_terminal() -> Result<Terminal<impl Backend>, Box<dyn std::error::Error>> {
+thread_local! {
+    // panics inside `catch_panics` are handled by the caller, the terminal stays as it is
+    static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+pub(crate) fn init_terminal() -> std::io::Result<Terminal<impl Backend>> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
     std::io::stdout().execute(crossterm::event::EnableBracketedPaste)?;
@@ -16,7 +23,7 @@ pub(crate) fn init_terminal() -> Result<Terminal<impl Backend>, Box<dyn std::err
     Ok(terminal)
 }
 
-pub(crate) fn restore_terminal() -> Result<(), Box<dyn std::error::Error>> {
+pub(crate) fn restore_terminal() -> std::io::Result<()> {
     std::io::stdout().execute(crossterm::event::DisableMouseCapture)?;
     std::io::stdout().execute(crossterm::event::DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
@@ -27,6 +34,9 @@ pub(crate) fn restore_terminal() -> Result<(), Box<dyn std::error::Error>> {
 pub(crate) fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
+        if CATCHING_PANICS.with(Cell::get) {
+            return;
+        }
         std::io::stdout()
             .execute(crossterm::event::DisableMouseCapture)
             .unwrap();
@@ -40,3 +50,36 @@ pub(crate) fn install_panic_hook() {
         original_hook(panic_info);
     }));
 }
+
+/// Runs `f`, returning the panic message instead of unwinding further if it panics.
+pub(crate) fn catch_panics<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    let was_catching = CATCHING_PANICS.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    CATCHING_PANICS.with(|catching| catching.set(was_catching));
+    result.map_err(|payload| panic_message(payload.as_ref()))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn caught_panics_return_their_message() {
+        assert_eq!(catch_panics(|| 1), Ok(1));
+        assert_eq!(
+            catch_panics(|| -> () { panic!("bad {}", "input") }),
+            Err("bad input".to_string())
+        );
+    }
+}
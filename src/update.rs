@@ -1,46 +1,1077 @@
 use std::{
+    collections::{BTreeMap, HashSet},
+    io::{Read, Write},
     mem,
     path::{Path, PathBuf},
     process::Stdio,
-    sync::{mpsc::Receiver, Arc, Mutex},
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex, PoisonError,
+    },
     thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use arboard::Clipboard;
 use crossterm::ExecutableCommand;
 use ratatui::layout::Rect;
 
 use crate::{
-    event, split_string, CommandWithoutOutput, CompletedCommand, CurrentView, Directory, File,
-    HintState, Mode, Model, Origin, Output, OutputType, StringType,
+    ansi, clipboard::Clipboard, error::VshellError, event, smart_case_starts_with, split_string,
+    tui, CommandWithoutOutput, CompletedCommand, Completion, Confirmation, ConfirmedAction,
+    CurrentView, Directory, DirectoryDisplay, Execution, File, HintState, HistoryEntry,
+    HistorySearch, Job, Mode, Model, Origin, Output, OutputSearch, OutputType, PaneSize, Phase,
+    Preview, Progress, Status, StringType,
 };
 
+// only the beginning of a file is read for the picker preview
+const PREVIEW_BYTES: u64 = 4096;
+
+fn read_preview(path: &Path) -> Preview {
+    let mut bytes = Vec::new();
+    let read =
+        std::fs::File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return Preview::Unreadable(e.to_string());
+    }
+    if bytes.contains(&0) {
+        return Preview::Binary;
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Preview::Text(text),
+        // the read may have cut a multi-byte character in half
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid_up_to = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid_up_to);
+            // SAFETY: we just truncated to the valid part
+            Preview::Text(String::from_utf8(bytes).unwrap())
+        }
+        Err(_) => Preview::Binary,
+    }
+}
+
+/// Finds the elements spanning from the word with the hint `beginning` to the one with the
+/// hint `end`.
+fn hint_range(
+    split_input: &[StringType],
+    beginning: u32,
+    end: u32,
+) -> Option<std::ops::RangeInclusive<usize>> {
+    let mut words = split_input
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| matches!(element, StringType::Word(_)))
+        .map(|(index, _)| index);
+    let start = words.nth(beginning as usize)?;
+    let end = match end.checked_sub(beginning) {
+        Some(0) => start,
+        Some(distance) => words.nth(distance as usize - 1)?,
+        None => return None,
+    };
+    Some(start..=end)
+}
+
+/// Splits command arguments on commas, `\,` is a literal comma.
+fn split_arguments(s: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut segment = String::new();
+    let mut escaped = false;
+
+    for c in s.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else if c == ',' && !escaped {
+            result.push(segment.clone());
+            segment.clear();
+        } else {
+            segment.push(c);
+            escaped = false;
+        }
+    }
+
+    result.push(segment);
+
+    result
+}
+
+/// Above this many lines `:pl` asks before running them.
+const PASTE_LINES_LIMIT: usize = 10;
+
+/// The commands in pasted text, one per line. Blank lines and lines that cannot be split
+/// into words, e.g. because of an unclosed quote, are left out.
+fn pasted_commands(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| shlex::split(line).is_some_and(|words| !words.is_empty()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The inputs of `history` from oldest to newest, one per line, for `:copyhistory`. With
+/// `with_outputs` every input is prefixed with `$ ` and followed by its output.
+fn history_text(history: &[CompletedCommand], with_outputs: bool) -> String {
+    history
+        .iter()
+        .map(|command| match with_outputs {
+            true => format!("$ {}\n{}", command.input, command.output),
+            false => command.input.clone(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Whether any command of `input`, after its aliases are expanded, recursively force-removes
+/// `/` or the home directory. Every command of a sequence and every stage of a pipeline is
+/// checked.
+fn is_catastrophic_rm(input: &str, aliases: &BTreeMap<String, String>) -> bool {
+    let expanded_input = expand_aliases(input, aliases);
+    let expanded_input = background_command(&expanded_input).unwrap_or(&expanded_input);
+    let Ok(commands) = split_sequence(expanded_input) else {
+        return false;
+    };
+    commands.into_iter().any(|(_, command)| {
+        split_pipeline(command)
+            .is_ok_and(|stages| stages.iter().any(|stage| is_catastrophic_rm_stage(stage)))
+    })
+}
+
+/// Whether the words of a single command recursively force-remove `/` or the home directory.
+fn is_catastrophic_rm_stage(words: &[String]) -> bool {
+    let mut tokens = words
+        .iter()
+        .map(|token| token.as_str())
+        .skip_while(|token| *token == "sudo")
+        .peekable();
+    match tokens.next() {
+        Some(program) if program == "rm" || program.ends_with("/rm") => {}
+        _ => return false,
+    }
+
+    let home = dirs::home_dir();
+    let is_dangerous_target = |target: &str| {
+        let target = target.strip_suffix("/*").unwrap_or(target);
+        let trimmed = target.trim_end_matches('/');
+        trimmed.is_empty()
+            || matches!(trimmed, "~" | "$HOME" | "${HOME}")
+            || home
+                .as_ref()
+                .is_some_and(|home| Path::new(trimmed) == home.as_path())
+    };
+
+    let (mut recursive, mut force, mut dangerous_target) = (false, false, false);
+    let mut only_targets_left = false;
+    for token in tokens {
+        if !only_targets_left && token == "--" {
+            only_targets_left = true;
+        } else if !only_targets_left && token.starts_with("--") {
+            recursive |= token == "--recursive";
+            force |= token == "--force";
+        } else if !only_targets_left && token.starts_with('-') && token.len() > 1 {
+            recursive |= token.contains(['r', 'R']);
+            force |= token.contains('f');
+        } else {
+            dangerous_target |= is_dangerous_target(token);
+        }
+    }
+    recursive && force && dangerous_target
+}
+
+/// Replaces the `{}` placeholders of `template` with `args` in order.
+fn fill_template(template: &str, args: &[String]) -> Result<String, String> {
+    let parts = template.split("{}").collect::<Vec<&str>>();
+    if parts.len() - 1 != args.len() {
+        return Err(format!(
+            "template expects {} arguments but got {}",
+            parts.len() - 1,
+            args.len()
+        ));
+    }
+    let mut filled = parts[0].to_string();
+    for (arg, part) in args.iter().zip(&parts[1..]) {
+        filled.push_str(arg);
+        filled.push_str(part);
+    }
+    Ok(filled)
+}
+
+/// Keeps the lines that contain `pattern`, or the ones that don't if `invert` is set.
+fn grep_lines(text: &str, pattern: &str, invert: bool) -> String {
+    text.split_inclusive('\n')
+        .filter(|line| line.contains(pattern) != invert)
+        .collect()
+}
+
+/// Keeps the whitespace-separated fields `first` to `last` of every line, counting from 1
+/// like `awk`. Lines with fewer fields become empty.
+fn select_columns(text: &str, first: u32, last: u32) -> String {
+    text.lines()
+        .map(|line| format!("{}\n", copy_words(line, first - 1, last - 1).trim_end()))
+        .collect()
+}
+
+/// Pads the keys of `KEY: value` or `KEY=value` lines so the values line up. The delimiter is
+/// the one that comes first in more lines, lines without it are kept as they are.
+fn align_pairs(text: &str) -> String {
+    let first_delimiter = |line: &str| line.chars().find(|c| matches!(c, ':' | '='));
+    let colons = text
+        .lines()
+        .filter(|line| first_delimiter(line) == Some(':'))
+        .count();
+    let equals = text
+        .lines()
+        .filter(|line| first_delimiter(line) == Some('='))
+        .count();
+    let delimiter = if equals > colons { '=' } else { ':' };
+    let pairs = text
+        .lines()
+        .map(|line| match line.split_once(delimiter) {
+            Some((key, value)) if !key.trim().is_empty() => Ok((key.trim(), value.trim())),
+            _ => Err(line),
+        })
+        .collect::<Vec<Result<(&str, &str), &str>>>();
+    let width = pairs
+        .iter()
+        .filter_map(|pair| pair.as_ref().ok())
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    pairs
+        .into_iter()
+        .map(|pair| match pair {
+            Ok((key, value)) => {
+                format!("{:width$} {} {}", key, delimiter, value, width = width)
+                    .trim_end()
+                    .to_string()
+                    + "\n"
+            }
+            Err(line) => format!("{}\n", line),
+        })
+        .collect()
+}
+
+/// Collects the words with the hints `beginning` to `end` and the whitespace between them.
+fn copy_words(output: &str, beginning: u32, end: u32) -> String {
+    let mut current = 0;
+    let mut words = String::new();
+    for element in split_string(output).iter() {
+        match element {
+            StringType::Word(w) => {
+                if current >= beginning && current <= end {
+                    words.push_str(w);
+                }
+                if current == end {
+                    break;
+                }
+                current += 1;
+            }
+            StringType::Newline(c) | StringType::Whitespace(c) => {
+                if current > beginning {
+                    words.push_str(c);
+                }
+            }
+            StringType::Tab => {
+                if current > beginning {
+                    words.push('\t');
+                }
+            }
+        }
+    }
+    words
+}
+
+/// Whether typing more letters could still select a different word.
+fn hint_is_complete(hint: &str, word_count: u32) -> bool {
+    match base26_to_base10(hint) {
+        // the hints one letter longer start at (index + 1) * 26
+        Ok(index) => (index + 1).saturating_mul(26) >= word_count,
+        Err(_) => false,
+    }
+}
+
+/// The index of the word with the hint `input`. Hints count in bijective base 26 so no two
+/// hints share an index: `a` to `z` are 0 to 25, `aa` is 26, `az` is 51 and `ba` is 52.
 fn base26_to_base10(input: &str) -> Result<u32, &'static str> {
-    let mut result = 0;
-    for (i, c) in input.chars().rev().enumerate() {
+    if input.is_empty() {
+        return Err("Missing hints");
+    }
+    let mut result = 0u32;
+    for c in input.chars() {
         let value = match c {
-            'a'..='z' => c as u32 - 'a' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 1,
             _ => return Err("Invalid Character"), // Invalid character
         };
-        result += value * 26u32.pow(i as u32);
+        result = result
+            .checked_mul(26)
+            .and_then(|result| result.checked_add(value))
+            .ok_or("Hint too long")?;
+    }
+    Ok(result - 1)
+}
+
+/// Expands `!!` to the last command and `!N` to entry `N` of the history pane anywhere in
+/// the input, except inside single quotes where bash leaves them alone as well.
+fn expand_history(input: &str, model: &Model) -> Result<String, String> {
+    let mut expanded = String::with_capacity(input.len());
+    let mut single_quote_open = false;
+    let mut escape = false;
+    let mut chars = input.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if escape {
+            escape = false;
+            expanded.push(c);
+            continue;
+        }
+        match c {
+            '\\' if !single_quote_open => escape = true,
+            '\'' => single_quote_open = !single_quote_open,
+            '!' if !single_quote_open => {
+                let rest = &input[index + 1..];
+                let designator_length = match rest.starts_with('!') {
+                    true => 1,
+                    false => rest
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(rest.len()),
+                };
+                if designator_length > 0 {
+                    let designator = &rest[..designator_length];
+                    let expansion = match designator {
+                        "!" => model
+                            .command_history
+                            .last()
+                            .map(|command| command.input.as_str()),
+                        number => number
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|number| model.history_input(number)),
+                    };
+                    match expansion {
+                        Some(expansion) => expanded.push_str(expansion),
+                        None => return Err(format!("!{}: event not found", designator)),
+                    }
+                    for _ in 0..designator_length {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        expanded.push(c);
+    }
+    Ok(expanded)
+}
+
+/// The characters of `input` that are neither quoted nor escaped, with their byte index.
+fn unquoted_chars(input: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut single_quote_open = false;
+    let mut double_quote_open = false;
+    let mut escape = false;
+    input.char_indices().filter(move |(_, c)| {
+        let unquoted = !single_quote_open && !double_quote_open && !escape;
+        match c {
+            '\'' if !double_quote_open && !escape => single_quote_open = !single_quote_open,
+            '"' if !single_quote_open && !escape => double_quote_open = !double_quote_open,
+            _ => {}
+        }
+        escape = *c == '\\' && !escape && !single_quote_open;
+        unquoted
+    })
+}
+
+/// Replaces `$VAR` and `${VAR}` with the value of the environment variable, unknown variables
+/// are empty. Like in a shell nothing is expanded within single quotes or after a backslash.
+/// The values are quoted so that they stay a single word and their content is taken literally.
+fn expand_variables(input: &str) -> String {
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+
+    let mut expanded = String::with_capacity(input.len());
+    let mut single_quote_open = false;
+    let mut double_quote_open = false;
+    let mut escape = false;
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c == '$' && !single_quote_open && !escape {
+            let name = match rest.strip_prefix('{') {
+                Some(braced) => braced
+                    .split_once('}')
+                    .map(|(name, _)| name)
+                    .filter(|name| !name.is_empty() && name.chars().all(is_name_char)),
+                None => rest
+                    .find(|c: char| !is_name_char(c))
+                    .map_or(Some(rest), |end| Some(&rest[..end]))
+                    .filter(|name| !name.is_empty()),
+            };
+            if let Some(name) = name {
+                let value = std::env::var(name).unwrap_or_default();
+                match double_quote_open {
+                    true => expanded.push_str(&value.replace('\\', "\\\\").replace('"', "\\\"")),
+                    false if value.is_empty() => {}
+                    false => expanded.push_str(&shlex::quote(&value)),
+                }
+                let braces = match rest.starts_with('{') {
+                    true => 2,
+                    false => 0,
+                };
+                rest = &rest[name.len() + braces..];
+                continue;
+            }
+        }
+        match c {
+            '\'' if !double_quote_open && !escape => single_quote_open = !single_quote_open,
+            '"' if !single_quote_open && !escape => double_quote_open = !double_quote_open,
+            _ => {}
+        }
+        escape = c == '\\' && !escape && !single_quote_open;
+        expanded.push(c);
+    }
+    expanded
+}
+
+/// Replaces words with an unquoted `*`, `?` or `[` by the sorted paths they match. Like in
+/// bash a pattern that matches nothing is kept as it is and hidden files only match patterns
+/// that start with a dot. Relative patterns are matched in `directory` if there is one, in the
+/// current directory otherwise.
+fn expand_globs(input: &str, directory: Option<&Path>) -> String {
+    fn expand_word(word: &str, pattern: &str, directory: Option<&Path>, expanded: &mut String) {
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..Default::default()
+        };
+        let directory = directory.filter(|_| !Path::new(pattern).is_absolute());
+        let pattern = match directory {
+            Some(directory) => format!(
+                "{}/{}",
+                glob::Pattern::escape(&directory.to_string_lossy()),
+                pattern
+            ),
+            None => pattern.to_string(),
+        };
+        let matches = glob::glob_with(&pattern, options)
+            .map(|paths| {
+                paths
+                    .filter_map(Result::ok)
+                    .map(|path| {
+                        let path = directory
+                            .and_then(|directory| path.strip_prefix(directory).ok())
+                            .unwrap_or(&path);
+                        shlex::quote(&path.to_string_lossy()).into_owned()
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        match matches.is_empty() {
+            true => expanded.push_str(word),
+            false => expanded.push_str(&matches.join(" ")),
+        }
+    }
+
+    let mut expanded = String::with_capacity(input.len());
+    // the word as typed and as a pattern in which only the unquoted characters are special
+    let mut word = String::new();
+    let mut pattern = String::new();
+    let mut is_glob = false;
+    let mut single_quote_open = false;
+    let mut double_quote_open = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let quoted = single_quote_open || double_quote_open;
+        match c {
+            '\'' if !double_quote_open => single_quote_open = !single_quote_open,
+            '"' if !single_quote_open => double_quote_open = !double_quote_open,
+            // within double quotes only some characters can be escaped
+            '\\' if !single_quote_open
+                && (!double_quote_open || matches!(chars.peek(), Some('$' | '`' | '"' | '\\'))) =>
+            {
+                word.push(c);
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped);
+                    pattern.push_str(&glob::Pattern::escape(&escaped.to_string()));
+                }
+                continue;
+            }
+            c if !quoted && (c.is_whitespace() || matches!(c, '|' | '&' | ';')) => {
+                match is_glob {
+                    true => expand_word(&word, &pattern, directory, &mut expanded),
+                    false => expanded.push_str(&word),
+                }
+                expanded.push(c);
+                word.clear();
+                pattern.clear();
+                is_glob = false;
+                continue;
+            }
+            c if quoted => pattern.push_str(&glob::Pattern::escape(&c.to_string())),
+            c => {
+                is_glob |= matches!(c, '*' | '?' | '[');
+                pattern.push(c);
+            }
+        }
+        word.push(c);
+    }
+    match is_glob {
+        true => expand_word(&word, &pattern, directory, &mut expanded),
+        false => expanded.push_str(&word),
+    }
+    expanded
+}
+
+/// When a command in a sequence runs, depending on how the previous one went.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Condition {
+    // first command or after `;`
+    Always,
+    // after `&&`
+    Succeeded,
+    // after `||`
+    Failed,
+}
+
+/// Splits `input` at `;`, `&&` and `||` that are not quoted. A trailing `;` is allowed, any
+/// other empty command is an error.
+fn split_sequence(input: &str) -> Result<Vec<(Condition, &str)>, String> {
+    let bytes = input.as_bytes();
+    let mut commands = Vec::new();
+    let mut condition = Condition::Always;
+    let mut start = 0;
+    let mut unquoted = unquoted_chars(input).peekable();
+    while let Some((index, c)) = unquoted.next() {
+        let (next_condition, separator) = match c {
+            ';' => (Condition::Always, ";"),
+            '&' if bytes.get(index + 1) == Some(&b'&') => (Condition::Succeeded, "&&"),
+            '|' if bytes.get(index + 1) == Some(&b'|') => (Condition::Failed, "||"),
+            _ => continue,
+        };
+        if input[start..index].trim().is_empty() {
+            return Err(format!("syntax error near `{}`", separator));
+        }
+        commands.push((condition, &input[start..index]));
+        condition = next_condition;
+        start = index + separator.len();
+        if separator.len() == 2 {
+            unquoted.next();
+        }
+    }
+    match input[start..].trim().is_empty() {
+        true if condition != Condition::Always => {
+            return Err("syntax error: unexpected end of input".to_string())
+        }
+        true => {}
+        false => commands.push((condition, &input[start..])),
+    }
+    Ok(commands)
+}
+
+/// Runs the commands of a sequence whose condition holds until `cancelled` returns true.
+/// Like in a shell, the sequence succeeds if the last command that ran succeeded.
+fn run_sequence(
+    commands: &[(Condition, &str)],
+    mut run: impl FnMut(&str) -> bool,
+    cancelled: impl Fn() -> bool,
+) -> bool {
+    let mut succeeded = true;
+    for (condition, command) in commands {
+        let runs = match condition {
+            Condition::Always => true,
+            Condition::Succeeded => succeeded,
+            Condition::Failed => !succeeded,
+        };
+        if !runs {
+            continue;
+        }
+        succeeded = run(command);
+        if cancelled() {
+            break;
+        }
+    }
+    succeeded
+}
+
+/// The command to run in the background if `input` ends with a `&` that is neither quoted
+/// nor part of `&&`.
+fn background_command(input: &str) -> Option<&str> {
+    let (index, c) = unquoted_chars(input)
+        .filter(|(_, c)| !c.is_whitespace())
+        .last()?;
+    let command = input[..index].trim_end();
+    if c != '&' || command.is_empty() || input[..index].ends_with('&') {
+        return None;
+    }
+    Some(command)
+}
+
+/// Splits `input` into the words of each stage of a pipeline. A `|` inside quotes, or as
+/// part of `||`, does not separate stages.
+fn split_pipeline(input: &str) -> Result<Vec<Vec<String>>, String> {
+    let bytes = input.as_bytes();
+    let pipes = unquoted_chars(input)
+        .filter(|(index, c)| {
+            *c == '|'
+                && bytes.get(index + 1) != Some(&b'|')
+                && (*index == 0 || bytes[index - 1] != b'|')
+        })
+        .map(|(index, _)| index)
+        .collect::<Vec<usize>>();
+
+    let mut stages = Vec::with_capacity(pipes.len() + 1);
+    let mut start = 0;
+    for end in pipes.into_iter().chain([input.len()]) {
+        match shlex::split(&input[start..end]) {
+            Some(words) if !words.is_empty() => stages.push(words),
+            _ => return Err("syntax error near `|`".to_string()),
+        }
+        start = end + 1;
+    }
+    Ok(stages)
+}
+
+/// How the commands of an input are run, the same for every command of a sequence and every
+/// stage of a pipeline.
+#[derive(Debug, Clone, Default)]
+struct RunOptions {
+    // passed on as `COLUMNS` and `LINES`
+    size: PaneSize,
+    // whether output is shown while the command is still running
+    stream: bool,
+    // background jobs read nothing, they would take keystrokes away from the ui otherwise
+    background: bool,
+    // where to run instead of the current directory, `cd` can't be used then
+    directory: Option<PathBuf>,
+}
+
+impl RunOptions {
+    fn new(size: PaneSize, stream: bool) -> Self {
+        RunOptions {
+            size,
+            stream,
+            background: false,
+            directory: None,
+        }
+    }
+}
+
+// how often a running command is checked for new output and for being cancelled
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reads stdout and stderr of a running child on their own threads so that the output can be
+/// shown before the command exits.
+struct StreamedOutput {
+    buffers: Arc<Mutex<(Vec<u8>, Vec<u8>)>>,
+    readers: Vec<thread::JoinHandle<()>>,
+    // how many bytes were written when the output was last reported
+    reported: usize,
+}
+
+impl StreamedOutput {
+    fn new(child: &mut std::process::Child) -> Self {
+        fn read_into(
+            mut pipe: impl Read + Send + 'static,
+            buffers: Arc<Mutex<(Vec<u8>, Vec<u8>)>>,
+            stderr: bool,
+        ) -> thread::JoinHandle<()> {
+            thread::spawn(move || {
+                let mut chunk = [0; 4096];
+                while let Ok(read @ 1..) = pipe.read(&mut chunk) {
+                    let mut buffers = buffers.lock().unwrap_or_else(PoisonError::into_inner);
+                    match stderr {
+                        true => buffers.1.extend_from_slice(&chunk[..read]),
+                        false => buffers.0.extend_from_slice(&chunk[..read]),
+                    }
+                }
+            })
+        }
+
+        let buffers = Arc::new(Mutex::new((Vec::new(), Vec::new())));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(read_into(stdout, Arc::clone(&buffers), false));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(read_into(stderr, Arc::clone(&buffers), true));
+        }
+        StreamedOutput {
+            buffers,
+            readers,
+            reported: 0,
+        }
+    }
+
+    /// Sends the output to the ui if something was written since it was last sent.
+    fn report(&mut self, origin: &Origin, progress: &Sender<Progress>) {
+        let buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        let written = buffers.0.len() + buffers.1.len();
+        if written == self.reported {
+            return;
+        }
+        self.reported = written;
+        let _ = progress.send(Progress::Output(Output {
+            origin: origin.clone(),
+            output_type: OutputType::Success(
+                crate::collapse_carriage_returns(&String::from_utf8_lossy(&buffers.0)),
+                crate::collapse_carriage_returns(&String::from_utf8_lossy(&buffers.1)),
+                None,
+            ),
+        }));
+    }
+
+    /// Waits until the child closed its stdout and stderr and returns all of its output.
+    fn finish(
+        self,
+        status: std::io::Result<std::process::ExitStatus>,
+    ) -> std::io::Result<std::process::Output> {
+        for reader in self.readers {
+            let _ = reader.join();
+        }
+        let (stdout, stderr) =
+            mem::take(&mut *self.buffers.lock().unwrap_or_else(PoisonError::into_inner));
+        status.map(|status| std::process::Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+/// Runs `execute` on a command thread. A panic becomes an error shown in the output pane so
+/// that a bug in vshell only fails the command that triggered it.
+fn without_panics(input: &str, execute: impl FnOnce() -> CompletedCommand) -> CompletedCommand {
+    tui::catch_panics(execute).unwrap_or_else(|message| CompletedCommand {
+        input: input.to_string(),
+        output: Output {
+            origin: Origin::Vshell,
+            output_type: OutputType::Error(
+                String::new(),
+                format!("vshell panicked while executing the command: {}", message),
+                None,
+            ),
+        },
+        ..Default::default()
+    })
+}
+
+/// The byte index of the `position`th character of `input`, or the end of `input` if it is
+/// shorter than that.
+fn char_position(input: &str, position: usize) -> usize {
+    input
+        .char_indices()
+        .nth(position)
+        .map_or(input.len(), |(index, _)| index)
+}
+
+// aliases may expand into other aliases, but only this many times
+const ALIAS_DEPTH_LIMIT: usize = 16;
+
+/// Replaces the first word of every command in `input` that names an alias, including commands
+/// after `|`, `&&`, `||`, `;` and `&`. Like in bash an alias is not expanded inside its own
+/// expansion.
+fn expand_aliases(input: &str, aliases: &BTreeMap<String, String>) -> String {
+    let is_separator = |c: char| matches!(c, '|' | '&' | ';');
+    let mut command_starts = vec![0];
+    command_starts.extend(
+        unquoted_chars(input)
+            .filter(|(_, c)| is_separator(*c))
+            .map(|(index, c)| index + c.len_utf8()),
+    );
+
+    let mut result = String::new();
+    let mut copied = 0;
+    for start in command_starts {
+        let word_start = start + (input[start..].len() - input[start..].trim_start().len());
+        let word_end = input[word_start..]
+            .find(|c: char| c.is_whitespace() || is_separator(c))
+            .map_or(input.len(), |end| word_start + end);
+        let mut expansion = input[word_start..word_end].to_string();
+        let mut expanded = Vec::new();
+        while expanded.len() < ALIAS_DEPTH_LIMIT {
+            let first_word = expansion.split_whitespace().next().unwrap_or_default();
+            let Some(alias) = aliases
+                .get(first_word)
+                .filter(|_| !expanded.iter().any(|name| name == first_word))
+            else {
+                break;
+            };
+            expanded.push(first_word.to_string());
+            expansion = format!("{}{}", alias, &expansion.trim_start()[first_word.len()..]);
+        }
+        if !expanded.is_empty() {
+            result.push_str(&input[copied..word_start]);
+            result.push_str(&expansion);
+            copied = word_end;
+        }
+    }
+    result.push_str(&input[copied..]);
+    result
+}
+
+// tools disagree on how to ask for their version, they are tried in this order
+const VERSION_ARGUMENTS: [&str; 3] = ["--version", "-V", "version"];
+
+/// `input` without the word in front of `cursor` and the whitespace between them, like ctrl+w
+/// in readline, and where the cursor ends up.
+fn delete_word_backward(input: &str, cursor: usize) -> (String, usize) {
+    let mut elements = split_string(&input[..cursor]);
+    while matches!(elements.last(), Some(element) if !matches!(element, StringType::Word(_))) {
+        elements.pop();
+    }
+    elements.pop();
+    let start = elements
+        .iter()
+        .map(|element| element.as_str().len())
+        .sum::<usize>();
+    (format!("{}{}", &input[..start], &input[cursor..]), start)
+}
+
+/// Where alt+left (`forward` is false) or alt+right moves the cursor: to the start of the word
+/// before it or to the end of the word after it. Words are the ones that get hints, so this
+/// stops at the same places as `:jb` and `:ja`.
+fn word_jump(input: &str, cursor: usize, forward: bool) -> usize {
+    let mut words = Vec::new();
+    let mut position = 0;
+    for element in split_string(input) {
+        let length = element.as_str().len();
+        if let StringType::Word(_) = element {
+            words.push((position, position + length));
+        }
+        position += length;
+    }
+    match forward {
+        true => words
+            .into_iter()
+            .map(|(_, end)| end)
+            .find(|end| *end > cursor)
+            .unwrap_or(input.len()),
+        false => words
+            .into_iter()
+            .rev()
+            .map(|(start, _)| start)
+            .find(|start| *start < cursor)
+            .unwrap_or(0),
+    }
+}
+
+/// The directories of the history that still exist, each once in the order they were first
+/// visited, for `:everywhere`.
+fn unique_directories(history: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    history
+        .iter()
+        .filter(|directory| directory.is_dir() && seen.insert(*directory))
+        .cloned()
+        .collect()
+}
+
+/// Splits `input` at `cursor` into the text that is kept and the text ctrl+k (`to_end`) or ctrl+u
+/// removes.
+fn kill_line(input: &str, cursor: usize, to_end: bool) -> (String, String) {
+    let (before, after) = input.split_at(cursor);
+    match to_end {
+        true => (before.to_string(), after.to_string()),
+        false => (after.to_string(), before.to_string()),
+    }
+}
+
+/// The start of the word that ends at `cursor` and whether it is in command position, i.e. the
+/// first word of the input or of a pipeline stage or sequence segment.
+fn completed_word(input: &str, cursor: usize) -> (usize, bool) {
+    let mut words = split_string(&input[..cursor])
+        .into_iter()
+        .filter_map(|element| match element {
+            StringType::Word(word) => Some(word),
+            _ => None,
+        })
+        .collect::<Vec<&str>>();
+    let word = match input[..cursor].ends_with(char::is_whitespace) {
+        true => "",
+        false => words.pop().unwrap_or(""),
+    };
+    let command_position = words.last().is_none_or(|previous| {
+        matches!(*previous, "|" | "||" | "&&" | ";") || previous.ends_with(';')
+    });
+    (cursor - word.len(), command_position)
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Where the program `name` would be run from, like `which`. Names with a `/` are paths,
+/// everything else is looked up on `$PATH`.
+pub(crate) fn find_executable(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name)).filter(|path| is_executable(path));
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|directory| directory.join(name))
+        .find(|path| is_executable(path))
+}
+
+/// Executables on `$PATH` for a word in command position, directory entries otherwise.
+/// Directories end with `/`.
+fn completion_candidates(word: &str, command_position: bool) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if command_position && !word.contains('/') {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        for directory in std::env::split_paths(&path) {
+            let Ok(entries) = directory.read_dir() else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(word) && is_executable(&entry.path()) {
+                    candidates.push(name);
+                }
+            }
+        }
+    } else {
+        let (directory, prefix) = match word.rfind('/') {
+            Some(index) => (&word[..=index], &word[index + 1..]),
+            None => ("", word),
+        };
+        let path = match directory.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None if directory.is_empty() => PathBuf::from("."),
+            None => PathBuf::from(directory),
+        };
+        if let Ok(entries) = path.read_dir() {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // like globs, hidden entries are only offered when asked for
+                if !name.starts_with(prefix) || name.starts_with('.') && !prefix.starts_with('.') {
+                    continue;
+                }
+                let separator = if entry.path().is_dir() { "/" } else { "" };
+                candidates.push(format!("{}{}{}", directory, name, separator));
+            }
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Quotes a completed word, keeping a leading `~/` unquoted so the home directory is expanded.
+fn quote_completion(candidate: &str) -> String {
+    match candidate.strip_prefix("~/") {
+        Some(rest) if !rest.is_empty() => format!("~/{}", shlex::quote(rest)),
+        _ => shlex::quote(candidate).to_string(),
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut end = first.len();
+    for candidate in &candidates[1..] {
+        end = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((index, _), _)| end.min(index));
     }
-    Ok(result)
+    &first[..end]
+}
+
+fn cd_command(directory: &Path) -> String {
+    format!("cd {}", shlex::quote(&directory.to_string_lossy()))
 }
 
 enum Command {
     Quit,
     Edit(Edit),
     Select(Option<usize>),
+    // `:s:#n` selects the command with the number `n`, see `:hn`
+    SelectCommandNumber(usize),
     JumpBefore(String),
     JumpAfter(String),
+    GotoChar(usize),
     Pin,
     CopyOutput(CopyOutput),
     Paste,
     ToggleHints,
-    ShellExecute(String, Option<String>),
+    ShellExecute(Option<String>, Option<String>),
     Replace(Replace),
     SwitchHistory,
-    ChoosePath,
+    ShowJobs,
+    Kill(String),
+    // whether to open the picker where it was last closed
+    ChoosePath(bool),
+    Repeat,
+    Profile(usize),
+    ToggleAsync,
+    TogglePreview,
+    ExpandPins,
+    SetDirectoryDisplay(DirectoryDisplay),
+    CopyWord,
+    RunRange(String, String),
+    // the pattern and whether to keep the lines that do not match
+    Grep(String, bool),
+    // the number of the history entry like `:s` takes it and what to copy of its output
+    CopyHistoryOutput(usize, CopyOutput),
+    // the first and last field, counting from 1
+    Columns(u32, u32),
+    Align,
+    ToggleHidden(usize),
+    Tokens,
+    TogglePasteGuard,
+    ToggleHistoryNumbers,
+    ToggleStream,
+    InsertDate,
+    InsertDirectory,
+    Record(Record),
+    Buffer(Buffer),
+    Pipe(String),
+    DefineTemplate(String, String),
+    Set(String, String),
+    // whether to include the outputs
+    CopyHistory(bool),
+    LastDirectory,
+    PasteLines,
+    RunAndPin,
+    QuitAfter(String),
+    DeleteHistoryEntry(usize),
+    Sleep(Duration),
+    Version(String),
+    // runs the command in every directory of the directory history
+    Everywhere(String),
+    ToggleTable,
+    // whether to remove the pinned commands as well
+    ClearHistory(bool),
+    DedupeHistory,
+    Abbreviate(String, String),
+    Alias(String, String),
+    ShowAliases,
+    SearchHistory(String),
+    FindInOutput(String),
+    Replay(usize),
+    Template(String, Vec<String>),
+    ShowHidden,
+    HistoryHere,
+    Watch(Duration, String),
+    Top,
+    Bottom,
+}
+
+enum Record {
+    Start(Option<PathBuf>),
+    Stop,
+}
+
+enum Buffer {
+    Save(String),
+    Show(String),
 }
 
 enum Replace {
@@ -59,6 +1090,45 @@ enum CopyOutput {
     Range(String, String),
 }
 
+/// The part of `output` that `:co` and `:clip` copy, nothing for a range that ends before it
+/// begins.
+fn copied_text(output: &str, copy_output: CopyOutput) -> Result<Option<String>, &'static str> {
+    match copy_output {
+        CopyOutput::All => Ok(Some(output.to_string())),
+        CopyOutput::Single(hint) => {
+            let index = base26_to_base10(&hint)?;
+            Ok(Some(copy_words(output, index, index)))
+        }
+        CopyOutput::Range(beginning, end) => {
+            let beginning_index = base26_to_base10(&beginning)?;
+            let end_index = base26_to_base10(&end)?;
+            if end_index < beginning_index {
+                return Ok(None);
+            }
+            Ok(Some(copy_words(output, beginning_index, end_index)))
+        }
+    }
+}
+
+/// Parses the hints of `:co`, `:clip` and `:c`, either a single hint or a range like `a,c`.
+fn copy_output(hints: &str) -> Result<CopyOutput, &'static str> {
+    fn hint(hint: &str) -> Result<String, &'static str> {
+        if hint.is_empty() {
+            return Err("Missing hints");
+        }
+        if !hint.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err("Invalid Character");
+        }
+        Ok(hint.to_string())
+    }
+
+    match hints.split_once(',') {
+        Some((_, end)) if end.contains(',') => Err("Invalid Command"),
+        Some((beginning, end)) => Ok(CopyOutput::Range(hint(beginning)?, hint(end)?)),
+        None => Ok(CopyOutput::Single(hint(hints)?)),
+    }
+}
+
 impl TryFrom<&str> for Command {
     type Error = &'static str;
 
@@ -66,32 +1136,10 @@ impl TryFrom<&str> for Command {
         fn create_replace_string<'a>(
             split_input: &'a [&str],
         ) -> Result<Vec<String>, <Command as TryFrom<&'a str>>::Error> {
-            fn split_string(s: &str) -> Vec<String> {
-                let mut result = Vec::new();
-                let mut segment = String::new();
-                let mut escaped = false;
-
-                for c in s.chars() {
-                    if c == '\\' && !escaped {
-                        escaped = true;
-                    } else if c == ',' && !escaped {
-                        result.push(segment.clone());
-                        segment.clear();
-                    } else {
-                        segment.push(c);
-                        escaped = false;
-                    }
-                }
-
-                result.push(segment);
-
-                result
-            }
-
             if split_input.len() < 2 {
                 return Err("Invalid Command");
             }
-            let replace_args = split_string(&split_input[1..].join(""));
+            let replace_args = split_arguments(&split_input[1..].join(""));
             if replace_args.len() != 2 {
                 return Err("Invalid Command");
             }
@@ -100,68 +1148,39 @@ impl TryFrom<&str> for Command {
         if input.is_empty() {
             return Err("Empty Command");
         }
-        let split_input = input.split(':').collect::<Vec<&str>>();
-        if split_input.len() > 2 {
-            return Err("Invalid Command");
+        if let Some(query) = input.strip_prefix('/') {
+            if query.is_empty() {
+                return Err("Missing pattern");
+            }
+            return Ok(Command::SearchHistory(query.to_string()));
         }
+        // only the first colon separates the command from its argument, the argument may
+        // contain colons itself, e.g. `:grep 12:30` or `:watch 1,date +%H:%M`
+        let split_input = input.splitn(2, ':').collect::<Vec<&str>>();
         match split_input[0] {
             "q" | "quit" | "exit" => Ok(Command::Quit),
             "c" | "change" => {
                 if split_input.len() != 2 {
                     return Err("Invalid Command");
                 }
-                match split_input[1].contains(',') {
-                    true => {
-                        let mut hints = split_input[1].split(',');
-                        let beginning_hint = hints.next().unwrap();
-                        let end_hint = hints.next().unwrap();
-                        if hints.next().is_some() {
-                            return Err("Invalid Command");
-                        }
-                        let mut beginning = String::new();
-                        for c in beginning_hint.chars() {
-                            if c.is_alphabetic() {
-                                beginning.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if beginning.is_empty() {
-                            return Err("Missing hints");
-                        }
-                        let mut end = String::new();
-                        for c in end_hint.chars() {
-                            if c.is_alphabetic() {
-                                end.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if end.is_empty() {
-                            return Err("Missing hints");
-                        }
+                match copy_output(split_input[1])? {
+                    CopyOutput::Single(hint) => Ok(Command::Edit(Edit::Single(hint))),
+                    CopyOutput::Range(beginning, end) => {
                         Ok(Command::Edit(Edit::Range(beginning, end)))
                     }
-                    false => {
-                        let mut hint = String::new();
-                        for c in split_input[1].chars() {
-                            if c.is_alphabetic() {
-                                hint.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if hint.is_empty() {
-                            return Err("Missing hints");
-                        }
-                        Ok(Command::Edit(Edit::Single(hint)))
-                    }
+                    // there is always at least one hint
+                    CopyOutput::All => Err("Missing hints"),
                 }
             }
             "s" | "select" => {
                 if split_input.len() != 2 {
                     return Ok(Command::Select(None));
                 }
+                if let Some(number) = split_input[1].strip_prefix('#') {
+                    return Ok(Command::SelectCommandNumber(
+                        number.parse::<usize>().map_err(|_| "Invalid Number")?,
+                    ));
+                }
                 let mut target = String::new();
                 for c in split_input[1].chars() {
                     if c.is_ascii_digit() {
@@ -174,6 +1193,18 @@ impl TryFrom<&str> for Command {
                     target.parse::<usize>().map_err(|_| "Invalid Number")?,
                 )))
             }
+            "sel" | "selecthint" => {
+                if split_input.len() != 2 || split_input[1].is_empty() {
+                    return Err("Missing hints");
+                }
+                if !split_input[1].chars().all(|c| c.is_ascii_lowercase()) {
+                    return Err("Invalid Character");
+                }
+                // the hints count the entries in the same order as the numbers do
+                Ok(Command::Select(Some(
+                    base26_to_base10(split_input[1])? as usize
+                )))
+            }
             "jb" | "jumpbefore" => {
                 if split_input.len() != 2 {
                     return Ok(Command::JumpBefore(String::new()));
@@ -202,67 +1233,101 @@ impl TryFrom<&str> for Command {
                 }
                 Ok(Command::JumpAfter(hint))
             }
-            "pin" => Ok(Command::Pin),
-            "p" | "paste" => Ok(Command::Paste),
-            "co" | "copyoutput" => {
-                if split_input.len() == 1 {
-                    return Ok(Command::CopyOutput(CopyOutput::All));
-                }
+            "gc" | "gotochar" => {
                 if split_input.len() != 2 {
-                    return Err("Invalid Command");
+                    return Err("Missing position");
                 }
-                match split_input[1].contains(',') {
-                    true => {
-                        let mut hints = split_input[1].split(',');
-                        let beginning_hint = hints.next().unwrap();
-                        let end_hint = hints.next().unwrap();
-                        if hints.next().is_some() {
-                            return Err("Invalid Command");
-                        }
-                        let mut beginning = String::new();
-                        for c in beginning_hint.chars() {
-                            if c.is_alphabetic() {
-                                beginning.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if beginning.is_empty() {
-                            return Err("Missing hints");
-                        }
-                        let mut end = String::new();
-                        for c in end_hint.chars() {
-                            if c.is_alphabetic() {
-                                end.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if end.is_empty() {
-                            return Err("Missing hints");
-                        }
-                        Ok(Command::CopyOutput(CopyOutput::Range(beginning, end)))
+                let mut target = String::new();
+                for c in split_input[1].chars() {
+                    if c.is_ascii_digit() {
+                        target.push(c);
+                    } else {
+                        return Err("Invalid Character");
                     }
-                    false => {
-                        let mut hint = String::new();
-                        for c in split_input[1].chars() {
-                            if c.is_alphabetic() {
-                                hint.push(c);
-                            } else {
-                                return Err("Invalid Character");
-                            }
-                        }
-                        if hint.is_empty() {
-                            return Err("Missing hints");
-                        }
-                        Ok(Command::CopyOutput(CopyOutput::Single(hint)))
+                }
+                Ok(Command::GotoChar(
+                    target.parse::<usize>().map_err(|_| "Invalid Number")?,
+                ))
+            }
+            "pin" => Ok(Command::Pin),
+            "ep" | "expandpins" => Ok(Command::ExpandPins),
+            "p" | "paste" => Ok(Command::Paste),
+            "co" | "copyoutput" => {
+                if split_input.len() == 1 {
+                    return Ok(Command::CopyOutput(CopyOutput::All));
+                }
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                Ok(Command::CopyOutput(copy_output(split_input[1])?))
+            }
+            "clip" | "clipo" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                let (number, hints) = match split_input[1].split_once(':') {
+                    Some((number, hints)) => (number, Some(hints)),
+                    None => (split_input[1], None),
+                };
+                let number = number
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| "Invalid Number")?;
+                let copy = match hints {
+                    Some(hints) => copy_output(hints)?,
+                    None => CopyOutput::All,
+                };
+                Ok(Command::CopyHistoryOutput(number, copy))
+            }
+            "cw" | "copyword" => Ok(Command::CopyWord),
+            "grep" | "grepv" => {
+                if split_input.len() != 2 || split_input[1].is_empty() {
+                    return Err("Missing pattern");
+                }
+                Ok(Command::Grep(
+                    split_input[1].to_string(),
+                    split_input[0] == "grepv",
+                ))
+            }
+            "col" | "columns" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                let (first, last) = split_input[1]
+                    .split_once(',')
+                    .unwrap_or((split_input[1], split_input[1]));
+                let first = first.trim().parse::<u32>().map_err(|_| "Invalid Number")?;
+                let last = last.trim().parse::<u32>().map_err(|_| "Invalid Number")?;
+                if first == 0 || last < first {
+                    return Err("Invalid Range");
+                }
+                Ok(Command::Columns(first, last))
+            }
+            "al" | "align" => Ok(Command::Align),
+            "rr" | "runrange" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                let (beginning, end) = split_input[1]
+                    .split_once(',')
+                    .unwrap_or((split_input[1], split_input[1]));
+                for hint in [beginning, end] {
+                    if hint.is_empty() {
+                        return Err("Missing hints");
+                    }
+                    if !hint.chars().all(|c| c.is_alphabetic()) {
+                        return Err("Invalid Character");
                     }
                 }
+                Ok(Command::RunRange(beginning.to_string(), end.to_string()))
             }
             "th" | "togglehints" => Ok(Command::ToggleHints),
+            "ta" | "toggleasync" => Ok(Command::ToggleAsync),
+            "tp" | "togglepreview" => Ok(Command::TogglePreview),
             "se" | "shellexecute" => {
                 if split_input.len() != 2 {
-                    return Err("Invalid Command");
+                    // without a shell the configured one is used
+                    return Ok(Command::ShellExecute(None, None));
                 }
 
                 match split_input[1].contains(',') {
@@ -270,9 +1335,12 @@ impl TryFrom<&str> for Command {
                         let mut args = split_input[1].split(',');
                         let shell = args.next().unwrap();
                         let prefix = args.collect::<Vec<&str>>().join(",");
-                        Ok(Command::ShellExecute(shell.to_string(), Some(prefix)))
+                        Ok(Command::ShellExecute(Some(shell.to_string()), Some(prefix)))
                     }
-                    false => Ok(Command::ShellExecute(split_input[1].to_string(), None)),
+                    false => Ok(Command::ShellExecute(
+                        Some(split_input[1].to_string()),
+                        None,
+                    )),
                 }
             }
             "rg" | "replaceglobal" => {
@@ -292,7 +1360,248 @@ impl TryFrom<&str> for Command {
                 )))
             }
             "sh" | "switchhistory" => Ok(Command::SwitchHistory),
-            "cp" | "choosepath" => Ok(Command::ChoosePath),
+            "jobs" | "showjobs" => Ok(Command::ShowJobs),
+            "kill" | "killjob" => {
+                if split_input.len() != 2 {
+                    return Err("Missing hints");
+                }
+                let hint = split_input[1];
+                if hint.is_empty() {
+                    return Err("Missing hints");
+                }
+                if !hint.chars().all(|c| c.is_ascii_lowercase()) {
+                    return Err("Invalid Character");
+                }
+                Ok(Command::Kill(hint.to_string()))
+            }
+            "ct" | "commenttoggle" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                Ok(Command::ToggleHidden(
+                    split_input[1]
+                        .parse::<usize>()
+                        .map_err(|_| "Invalid Number")?,
+                ))
+            }
+            "dh" | "deletehistory" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                Ok(Command::DeleteHistoryEntry(
+                    split_input[1]
+                        .parse::<usize>()
+                        .map_err(|_| "Invalid Number")?,
+                ))
+            }
+            "shd" | "showhidden" => Ok(Command::ShowHidden),
+            "here" | "historyhere" => Ok(Command::HistoryHere),
+            "tk" | "tokens" => Ok(Command::Tokens),
+            "pg" | "pasteguard" => Ok(Command::TogglePasteGuard),
+            "hn" | "historynumbers" => Ok(Command::ToggleHistoryNumbers),
+            "stream" | "togglestream" => Ok(Command::ToggleStream),
+            "id" | "insertdate" => Ok(Command::InsertDate),
+            "ic" | "insertcwd" => Ok(Command::InsertDirectory),
+            "set" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once([',', ' ']) {
+                    Some((key, value)) => Ok(Command::Set(
+                        key.trim().to_string(),
+                        value.trim().to_string(),
+                    )),
+                    None => Err("Invalid Command"),
+                }
+            }
+            "ld" | "lastdirectory" => Ok(Command::LastDirectory),
+            "pl" | "pastelines" => Ok(Command::PasteLines),
+            "rp" | "runandpin" => Ok(Command::RunAndPin),
+            "qa" | "quitafter" => match split_input.get(1).map(|input| input.trim()) {
+                Some(input) if !input.is_empty() => Ok(Command::QuitAfter(input.to_string())),
+                _ => Err("Missing command"),
+            },
+            "table" | "toggletable" => Ok(Command::ToggleTable),
+            "ddh" | "dedupe" | "dedupehistory" => Ok(Command::DedupeHistory),
+            "clh" | "clearhistory" => match split_input.get(1) {
+                None => Ok(Command::ClearHistory(false)),
+                Some(&"pins") => Ok(Command::ClearHistory(true)),
+                Some(_) => Err("Invalid Command"),
+            },
+            "ab" | "abbrev" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once(',') {
+                    Some((key, expansion))
+                        if !key.is_empty() && !key.contains(char::is_whitespace) =>
+                    {
+                        Ok(Command::Abbreviate(key.to_string(), expansion.to_string()))
+                    }
+                    _ => Err("Invalid Command"),
+                }
+            }
+            "alias" => match split_input.get(1) {
+                None => Ok(Command::ShowAliases),
+                Some(alias) => match alias.split_once(',') {
+                    Some((name, expansion))
+                        if !name.is_empty()
+                            && !name.contains(|c: char| c.is_whitespace() || "|&;".contains(c)) =>
+                    {
+                        Ok(Command::Alias(
+                            name.to_string(),
+                            expansion.trim().to_string(),
+                        ))
+                    }
+                    _ => Err("Invalid Command"),
+                },
+            },
+            "replay" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].parse::<usize>() {
+                    Ok(0) | Err(_) => Err("Invalid Number"),
+                    Ok(amount) => Ok(Command::Replay(amount)),
+                }
+            }
+            "ch" | "copyhistory" => match split_input.get(1) {
+                None => Ok(Command::CopyHistory(false)),
+                Some(&"outputs") => Ok(Command::CopyHistory(true)),
+                Some(_) => Err("Invalid Command"),
+            },
+            "td" | "templatedefine" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once(',') {
+                    Some((name, template)) if !name.is_empty() && !template.is_empty() => Ok(
+                        Command::DefineTemplate(name.to_string(), template.to_string()),
+                    ),
+                    _ => Err("Invalid Command"),
+                }
+            }
+            "tpl" | "template" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once(',') {
+                    Some((name, args)) => {
+                        Ok(Command::Template(name.to_string(), split_arguments(args)))
+                    }
+                    None => Ok(Command::Template(split_input[1].to_string(), Vec::new())),
+                }
+            }
+            "record" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once(',') {
+                    Some(("start", path)) if !path.is_empty() => {
+                        Ok(Command::Record(Record::Start(Some(PathBuf::from(path)))))
+                    }
+                    None if split_input[1] == "start" => Ok(Command::Record(Record::Start(None))),
+                    None if split_input[1] == "stop" => Ok(Command::Record(Record::Stop)),
+                    _ => Err("Invalid Command"),
+                }
+            }
+            "pipe" => {
+                if split_input.len() != 2 || split_input[1].trim().is_empty() {
+                    return Err("Missing command");
+                }
+                Ok(Command::Pipe(split_input[1].trim().to_string()))
+            }
+            "buf" | "buffer" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1].split_once([',', ' ']) {
+                    Some(("save", name)) if !name.is_empty() => {
+                        Ok(Command::Buffer(Buffer::Save(name.to_string())))
+                    }
+                    Some(("show", name)) if !name.is_empty() => {
+                        Ok(Command::Buffer(Buffer::Show(name.to_string())))
+                    }
+                    _ => Err("Invalid Command"),
+                }
+            }
+            "dd" | "directorydisplay" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                match split_input[1] {
+                    "absolute" => Ok(Command::SetDirectoryDisplay(DirectoryDisplay::Absolute)),
+                    "home" => Ok(Command::SetDirectoryDisplay(DirectoryDisplay::Home)),
+                    "relative" => Ok(Command::SetDirectoryDisplay(
+                        DirectoryDisplay::CommonAncestor,
+                    )),
+                    _ => Err("Invalid Command"),
+                }
+            }
+            "cp" | "choosepath" => Ok(Command::ChoosePath(false)),
+            "cp!" | "choosepath!" => Ok(Command::ChoosePath(true)),
+            "." | "repeat" => Ok(Command::Repeat),
+            "top" => Ok(Command::Top),
+            "bottom" => Ok(Command::Bottom),
+            "w" | "watch" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                let (interval, command) =
+                    split_input[1].split_once(',').ok_or("Invalid Command")?;
+                let interval = interval.parse::<f64>().map_err(|_| "Invalid Number")?;
+                if !interval.is_finite() || interval <= 0.0 {
+                    return Err("Invalid Number");
+                }
+                if command.trim().is_empty() {
+                    return Err("Missing command");
+                }
+                Ok(Command::Watch(
+                    Duration::from_secs_f64(interval),
+                    command.to_string(),
+                ))
+            }
+            "f" | "find" => {
+                if split_input.len() != 2 || split_input[1].is_empty() {
+                    return Err("Missing pattern");
+                }
+                Ok(Command::FindInOutput(split_input[1].to_string()))
+            }
+            "ev" | "everywhere" | "execinalldirs" => {
+                if split_input.len() != 2 || split_input[1].trim().is_empty() {
+                    return Err("Invalid Command");
+                }
+                Ok(Command::Everywhere(split_input[1].trim().to_string()))
+            }
+            "ver" | "whichversion" => {
+                if split_input.len() != 2 || split_input[1].trim().is_empty() {
+                    return Err("Invalid Command");
+                }
+                Ok(Command::Version(split_input[1].trim().to_string()))
+            }
+            "sleep" => {
+                if split_input.len() != 2 {
+                    return Err("Invalid Command");
+                }
+                let seconds = split_input[1]
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| "Invalid Number")?;
+                if !seconds.is_finite() || seconds < 0.0 {
+                    return Err("Invalid Number");
+                }
+                Ok(Command::Sleep(Duration::from_secs_f64(seconds)))
+            }
+            "profile" => {
+                if split_input.len() != 2 {
+                    return Ok(Command::Profile(10));
+                }
+                Ok(Command::Profile(
+                    split_input[1]
+                        .parse::<usize>()
+                        .map_err(|_| "Invalid Number")?,
+                ))
+            }
             _ => Err("Invalid Command"),
         }
     }
@@ -302,7 +1611,7 @@ pub(crate) fn update(
     model_lock: &Arc<Mutex<Model>>,
     event: event::Event,
     clipboard: &mut Clipboard,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), VshellError> {
     fn has_open_quote(s: &str) -> Option<char> {
         let mut single_quote_open = false;
         let mut double_quote_open = false;
@@ -364,7 +1673,11 @@ pub(crate) fn update(
         Some(children)
     }
 
-    fn paste(text_to_insert: &str, model: &mut Model) -> Result<(), Box<dyn std::error::Error>> {
+    fn paste(text_to_insert: &str, model: &mut Model) -> Result<(), VshellError> {
+        let text_to_insert = match model.config.paste_guard {
+            true => text_to_insert.trim_end_matches(['\n', '\r']),
+            false => text_to_insert,
+        };
         match &model.current_command {
             CurrentView::CommandWithoutOutput(command) => {
                 if command.cursor_position == command.input.len() as u64 {
@@ -408,27 +1721,285 @@ pub(crate) fn update(
         }
     }
 
-    fn execute_command(command_input: &str, receiver: Receiver<()>) -> CompletedCommand {
-        // SAFETY: our shell handles input validation so this will not fail
-        let command_list = shlex::split(command_input).unwrap();
+    /// Runs the stages of a pipeline with the stdout of each stage connected to the stdin of
+    /// the next one. Like in bash the exit code is the one of the last stage while the stderr
+    /// of every stage is shown.
+    fn execute_pipeline(
+        command_input: &str,
+        stages: Vec<Vec<String>>,
+        receiver: &Receiver<()>,
+        options: &RunOptions,
+        progress: &Sender<Progress>,
+    ) -> CompletedCommand {
+        let _ = progress.send(Progress::Phase(Phase::Spawning));
+        let stage_count = stages.len();
+        let mut children: Vec<std::process::Child> = Vec::with_capacity(stage_count);
+        let mut stderr_readers = Vec::new();
+        let mut previous_stdout = None;
+        for (index, stage) in stages.iter().enumerate() {
+            let mut command = std::process::Command::new(&stage[0]);
+            command
+                .args(stage[1..].iter().filter(|s| !s.is_empty()))
+                .envs(
+                    (options.size.columns > 0)
+                        .then(|| {
+                            [
+                                ("COLUMNS", options.size.columns.to_string()),
+                                ("LINES", options.size.lines.to_string()),
+                            ]
+                        })
+                        .into_iter()
+                        .flatten(),
+                )
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            if let Some(directory) = &options.directory {
+                command.current_dir(directory);
+            }
+            if let Some(stdout) = previous_stdout.take() {
+                command.stdin(Stdio::from(stdout));
+            } else if options.background {
+                command.stdin(Stdio::null());
+            }
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    for mut child in children {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    let (error_string, exit_code) = match e.kind() {
+                        std::io::ErrorKind::NotFound => {
+                            (format!("Command not found: {}", stage[0]), Some(127))
+                        }
+                        std::io::ErrorKind::PermissionDenied => {
+                            ("Permission denied".to_string(), Some(126))
+                        }
+                        _ => (format!("Could not spawn process: {}", e), None),
+                    };
+                    return CompletedCommand {
+                        input: command_input.to_string(),
+                        output: Output {
+                            origin: Origin::Vshell,
+                            output_type: OutputType::Error(String::new(), error_string, exit_code),
+                        },
+                        ..Default::default()
+                    };
+                }
+            };
+            if index + 1 < stage_count {
+                previous_stdout = child.stdout.take();
+                // read on the side so that a stage never blocks on a full stderr pipe
+                if let Some(mut stderr) = child.stderr.take() {
+                    stderr_readers.push(thread::spawn(move || {
+                        let mut buffer = Vec::new();
+                        let _ = stderr.read_to_end(&mut buffer);
+                        buffer
+                    }));
+                }
+            }
+            children.push(child);
+        }
+
+        let _ = progress.send(Progress::Phase(Phase::Running));
+        // SAFETY: there are at least two stages
+        let mut last = children.pop().unwrap();
+        let mut streamed_output = StreamedOutput::new(&mut last);
+        loop {
+            if !matches!(last.try_wait(), Ok(None)) {
+                break;
+            }
+            if receiver.recv_timeout(OUTPUT_POLL_INTERVAL).is_ok() {
+                let _ = last.kill();
+                for child in children.iter_mut() {
+                    let _ = child.kill();
+                }
+                break;
+            }
+            if options.stream {
+                streamed_output.report(&Origin::Vshell, progress);
+            }
+        }
+        let output = streamed_output.finish(last.wait());
+        // earlier stages exit once the next one stops reading
+        for mut child in children {
+            let _ = child.wait();
+        }
+        let mut stderr = stderr_readers
+            .into_iter()
+            .flat_map(|reader| reader.join().unwrap_or_default())
+            .collect::<Vec<u8>>();
+        let output = output.map(|mut output| {
+            stderr.append(&mut output.stderr);
+            output.stderr = stderr;
+            output
+        });
+        CompletedCommand::new(command_input.to_string(), output, Origin::Vshell)
+    }
+
+    /// Runs the commands of a sequence one after the other in the same way bash does. Their
+    /// outputs are combined and the exit code is the one of the last command that ran.
+    fn execute_sequence(
+        command_input: &str,
+        commands: Vec<(Condition, &str)>,
+        receiver: &Receiver<()>,
+        options: &RunOptions,
+        progress: &Sender<Progress>,
+    ) -> CompletedCommand {
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let mut exit_code = Some(0);
+        let succeeded = run_sequence(
+            &commands,
+            |command| {
+                let completed_command =
+                    // aliases were expanded for the whole sequence
+                    execute_command(command.trim(), &BTreeMap::new(), receiver, options, progress);
+                let (succeeded, (out, err, code)) = match completed_command.output.output_type {
+                    OutputType::Success(out, err, code) => (true, (out, err, code)),
+                    OutputType::Error(out, err, code) => (false, (out, err, code)),
+                    OutputType::Empty => (true, (String::new(), String::new(), Some(0))),
+                };
+                stdout.push_str(&out);
+                stderr.push_str(&err);
+                exit_code = code;
+                succeeded
+            },
+            // the sender is dropped when the command is cancelled
+            || !matches!(receiver.try_recv(), Err(TryRecvError::Empty)),
+        );
+        CompletedCommand {
+            input: command_input.to_string(),
+            output: Output {
+                origin: Origin::Vshell,
+                output_type: match succeeded {
+                    true => OutputType::Success(stdout, stderr, exit_code),
+                    false => OutputType::Error(stdout, stderr, exit_code),
+                },
+            },
+            ..Default::default()
+        }
+    }
+
+    fn execute_sleep(
+        command_input: &str,
+        duration: Duration,
+        receiver: &Receiver<()>,
+        progress: &Sender<Progress>,
+    ) -> CompletedCommand {
+        let _ = progress.send(Progress::Phase(Phase::Running));
+        // anything but a timeout means ctrl+c was pressed
+        let output_type = match receiver.recv_timeout(duration) {
+            Err(RecvTimeoutError::Timeout) => {
+                OutputType::Success(String::new(), String::new(), Some(0))
+            }
+            _ => OutputType::Error(String::new(), "sleep: interrupted".to_string(), None),
+        };
+        CompletedCommand {
+            input: command_input.to_string(),
+            output: Output {
+                origin: Origin::Vshell,
+                output_type,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn execute_command(
+        command_input: &str,
+        aliases: &BTreeMap<String, String>,
+        receiver: &Receiver<()>,
+        options: &RunOptions,
+        progress: &Sender<Progress>,
+    ) -> CompletedCommand {
+        // the ui may not be listening, e.g. for commands that run synchronously
+        let _ = progress.send(Progress::Phase(Phase::Parsing));
+        let expanded_input = expand_aliases(command_input, aliases);
+        match split_sequence(&expanded_input) {
+            Ok(commands) if commands.len() > 1 => {
+                return execute_sequence(command_input, commands, receiver, options, progress)
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return CompletedCommand {
+                    input: command_input.to_string(),
+                    output: Output {
+                        origin: Origin::Vshell,
+                        output_type: OutputType::Error(String::new(), e, Some(2)),
+                    },
+                    ..Default::default()
+                }
+            }
+        }
+        // `:sleep` from the history, or as part of a sequence
+        if let Some(Ok(Command::Sleep(duration))) = expanded_input
+            .trim()
+            .strip_prefix(':')
+            .map(Command::try_from)
+        {
+            return execute_sleep(command_input, duration, receiver, progress);
+        }
+        let mut stages = match split_pipeline(&expand_globs(
+            &expand_variables(&expanded_input),
+            options.directory.as_deref(),
+        )) {
+            Ok(stages) => stages,
+            Err(e) => {
+                return CompletedCommand {
+                    input: command_input.to_string(),
+                    output: Output {
+                        origin: Origin::Vshell,
+                        output_type: OutputType::Error(String::new(), e, Some(2)),
+                    },
+                    ..Default::default()
+                }
+            }
+        };
+        if stages.len() > 1 {
+            return execute_pipeline(command_input, stages, receiver, options, progress);
+        }
+        let command_list = stages.remove(0);
 
         if command_list[0] == "cd" {
-            if command_list.len() == 1 {
+            if options.directory.is_some() {
+                CompletedCommand {
+                    input: command_input.to_string(),
+                    output: Output {
+                        origin: Origin::Vshell,
+                        output_type: OutputType::Error(
+                            String::new(),
+                            "cd: not available when running in another directory".to_string(),
+                            Some(1),
+                        ),
+                    },
+                    ..Default::default()
+                }
+            } else if command_list.len() == 1 {
                 match dirs::home_dir() {
                     Some(home) => match std::env::set_current_dir(home) {
                         Ok(_) => CompletedCommand {
                             input: command_input.to_string(),
                             output: Output {
                                 origin: Origin::Vshell,
-                                output_type: OutputType::Success(String::new(), String::new()),
+                                output_type: OutputType::Success(
+                                    String::new(),
+                                    String::new(),
+                                    Some(0),
+                                ),
                             },
+                            ..Default::default()
                         },
                         Err(e) => CompletedCommand {
                             input: command_input.to_string(),
                             output: Output {
                                 origin: Origin::Vshell,
-                                output_type: OutputType::Error(String::new(), format!("cd: {}", e)),
+                                output_type: OutputType::Error(
+                                    String::new(),
+                                    format!("cd: {}", e),
+                                    Some(1),
+                                ),
                             },
+                            ..Default::default()
                         },
                     },
                     None => CompletedCommand {
@@ -438,8 +2009,10 @@ pub(crate) fn update(
                             output_type: OutputType::Error(
                                 String::new(),
                                 "cd: could not find home directory".to_string(),
+                                Some(1),
                             ),
                         },
+                        ..Default::default()
                     },
                 }
             } else if command_list.len() != 2 {
@@ -450,20 +2023,27 @@ pub(crate) fn update(
                         output_type: OutputType::Error(
                             String::new(),
                             "cd: incorrect number of arguments".to_string(),
+                            Some(1),
                         ),
                     },
+                    ..Default::default()
                 }
-            } else if command_list[1].contains('~') {
+            } else if command_list[1] == "~" || command_list[1].starts_with("~/") {
                 match dirs::home_dir() {
                     Some(home) => {
-                        let new_path = command_list[1].replace('~', &home.to_string_lossy());
+                        let new_path = command_list[1].replacen('~', &home.to_string_lossy(), 1);
                         match std::env::set_current_dir(new_path) {
                             Ok(_) => CompletedCommand {
                                 input: command_input.to_string(),
                                 output: Output {
                                     origin: Origin::Vshell,
-                                    output_type: OutputType::Success(String::new(), String::new()),
+                                    output_type: OutputType::Success(
+                                        String::new(),
+                                        String::new(),
+                                        Some(0),
+                                    ),
                                 },
+                                ..Default::default()
                             },
                             Err(e) => CompletedCommand {
                                 input: command_input.to_string(),
@@ -472,8 +2052,10 @@ pub(crate) fn update(
                                     output_type: OutputType::Error(
                                         String::new(),
                                         format!("cd: {}", e),
+                                        Some(1),
                                     ),
                                 },
+                                ..Default::default()
                             },
                         }
                     }
@@ -484,8 +2066,10 @@ pub(crate) fn update(
                             output_type: OutputType::Error(
                                 String::new(),
                                 "cd: could not find home directory".to_string(),
+                                Some(1),
                             ),
                         },
+                        ..Default::default()
                     },
                 }
             } else {
@@ -494,98 +2078,346 @@ pub(crate) fn update(
                         input: command_input.to_string(),
                         output: Output {
                             origin: Origin::Vshell,
-                            output_type: OutputType::Success(String::new(), String::new()),
+                            output_type: OutputType::Success(String::new(), String::new(), Some(0)),
                         },
+                        ..Default::default()
                     },
                     Err(e) => CompletedCommand {
                         input: command_input.to_string(),
                         output: Output {
                             origin: Origin::Vshell,
-                            output_type: OutputType::Error(String::new(), format!("cd: {}", e)),
+                            output_type: OutputType::Error(
+                                String::new(),
+                                format!("cd: {}", e),
+                                Some(1),
+                            ),
                         },
+                        ..Default::default()
                     },
                 }
             }
         } else {
-            let executed_command = std::process::Command::new(&command_list[0])
-                .args(
-                    &command_list[1..]
-                        .iter()
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<&String>>(),
-                )
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn();
+            execute_program(
+                command_input,
+                &command_list,
+                None,
+                receiver,
+                options,
+                progress,
+            )
+        }
+    }
 
-            match executed_command {
-                Err(e) => {
-                    let error_string = match e.kind() {
-                        std::io::ErrorKind::NotFound => {
-                            format!("Command not found: {}", command_list[0])
-                        }
-                        std::io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
-                        _ => format!("Could not spawn process: {}", e),
-                    };
-                    CompletedCommand {
-                        input: command_input.to_string(),
-                        output: Output {
-                            origin: Origin::Vshell,
-                            output_type: OutputType::Error(String::new(), error_string),
-                        },
+    /// Runs a single program, `command_list[0]`, with the remaining arguments. `stdin` is written
+    /// to its standard input, without it the standard input is inherited unless the program runs
+    /// in the background.
+    fn execute_program(
+        command_input: &str,
+        command_list: &[String],
+        stdin: Option<String>,
+        receiver: &Receiver<()>,
+        options: &RunOptions,
+        progress: &Sender<Progress>,
+    ) -> CompletedCommand {
+        let _ = progress.send(Progress::Phase(Phase::Spawning));
+        let mut command = std::process::Command::new(&command_list[0]);
+        command
+            .args(
+                command_list[1..]
+                    .iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<&String>>(),
+            )
+            .envs(
+                // before the first frame is drawn the size is unknown
+                (options.size.columns > 0)
+                    .then(|| {
+                        [
+                            ("COLUMNS", options.size.columns.to_string()),
+                            ("LINES", options.size.lines.to_string()),
+                        ]
+                    })
+                    .into_iter()
+                    .flatten(),
+            )
+            .stdin(match (&stdin, options.background) {
+                (Some(_), _) => Stdio::piped(),
+                (None, true) => Stdio::null(),
+                (None, false) => Stdio::inherit(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(directory) = &options.directory {
+            command.current_dir(directory);
+        }
+        let executed_command = command.spawn();
+
+        match executed_command {
+            Err(e) => {
+                // the exit codes a shell would report
+                let (error_string, exit_code) = match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        (format!("Command not found: {}", command_list[0]), Some(127))
+                    }
+                    std::io::ErrorKind::PermissionDenied => {
+                        ("Permission denied".to_string(), Some(126))
                     }
+                    _ => (format!("Could not spawn process: {}", e), None),
+                };
+                CompletedCommand {
+                    input: command_input.to_string(),
+                    output: Output {
+                        origin: Origin::Vshell,
+                        output_type: OutputType::Error(String::new(), error_string, exit_code),
+                    },
+                    ..Default::default()
                 }
-                Ok(mut executed_command) => {
-                    loop {
-                        if executed_command.try_wait().is_err()
-                            || (executed_command.try_wait().is_ok()
-                                && executed_command.try_wait().unwrap().is_some())
-                        {
-                            break;
-                        }
-
-                        if receiver.try_recv().is_ok() {
-                            let result = executed_command.kill();
+            }
+            Ok(mut executed_command) => {
+                let _ = progress.send(Progress::Phase(Phase::Running));
+                // written on its own thread so a command that writes output before reading all
+                // of its input can't block
+                if let (Some(stdin), Some(mut child_stdin)) = (stdin, executed_command.stdin.take())
+                {
+                    thread::spawn(move || {
+                        // the command may exit without reading everything
+                        let _ = child_stdin.write_all(stdin.as_bytes());
+                    });
+                }
+                let mut streamed_output = StreamedOutput::new(&mut executed_command);
+                loop {
+                    if executed_command.try_wait().is_err()
+                        || (executed_command.try_wait().is_ok()
+                            && executed_command.try_wait().unwrap().is_some())
+                    {
+                        break;
+                    }
 
-                            if let Err(e) = result {
-                                let error_string = match e.kind() {
-                                    std::io::ErrorKind::NotFound => {
-                                        format!("Command not found: {}", command_list[0])
-                                    }
-                                    std::io::ErrorKind::PermissionDenied => {
-                                        "Permission denied".to_string()
-                                    }
-                                    _ => format!("Could not kill process: {}", e),
-                                };
+                    if receiver.recv_timeout(OUTPUT_POLL_INTERVAL).is_ok() {
+                        let result = executed_command.kill();
 
-                                return CompletedCommand {
-                                    input: command_input.to_string(),
-                                    output: Output {
-                                        origin: Origin::Vshell,
-                                        output_type: OutputType::Error(String::new(), error_string),
-                                    },
-                                };
-                            }
+                        if let Err(e) = result {
+                            let error_string = match e.kind() {
+                                std::io::ErrorKind::NotFound => {
+                                    format!("Command not found: {}", command_list[0])
+                                }
+                                std::io::ErrorKind::PermissionDenied => {
+                                    "Permission denied".to_string()
+                                }
+                                _ => format!("Could not kill process: {}", e),
+                            };
 
-                            break;
+                            return CompletedCommand {
+                                input: command_input.to_string(),
+                                output: Output {
+                                    origin: Origin::Vshell,
+                                    output_type: OutputType::Error(
+                                        String::new(),
+                                        error_string,
+                                        None,
+                                    ),
+                                },
+                                ..Default::default()
+                            };
                         }
-                    }
-
-                    let executed_command = executed_command.wait_with_output();
 
-                    CompletedCommand::new(
-                        command_input.to_string(),
-                        executed_command,
-                        Origin::Vshell,
-                    )
+                        break;
+                    }
+                    if options.stream {
+                        streamed_output.report(&Origin::Vshell, progress);
+                    }
+                }
+
+                let executed_command = streamed_output.finish(executed_command.wait());
+
+                CompletedCommand::new(command_input.to_string(), executed_command, Origin::Vshell)
+            }
+        }
+    }
+
+    /// Runs `execute` on its own thread so the ui keeps rendering and ctrl+c can cancel it.
+    fn spawn_command(
+        model_lock: &Arc<Mutex<Model>>,
+        model: &mut Model,
+        input: String,
+        execute: impl FnOnce(&str, &Receiver<()>, Sender<Progress>) -> CompletedCommand + Send + 'static,
+    ) {
+        spawn_command_then(model_lock, model, input, execute, |_, _| {});
+    }
+
+    /// Like `spawn_command`, `then` is called once the command is in the history with whether
+    /// it was cancelled.
+    fn spawn_command_then(
+        model_lock: &Arc<Mutex<Model>>,
+        model: &mut Model,
+        input: String,
+        execute: impl FnOnce(&str, &Receiver<()>, Sender<Progress>) -> CompletedCommand + Send + 'static,
+        then: impl FnOnce(&mut Model, bool) + Send + 'static,
+    ) {
+        let thread_model_lock = Arc::clone(model_lock);
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (progress_tx, progress_rx) = std::sync::mpsc::channel::<Progress>();
+        // recorded before `cd` can change it
+        let directory = std::env::current_dir().ok();
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let mut completed_command =
+                without_panics(&input, || execute(input.as_str(), &rx, progress_tx));
+            completed_command.duration = Some(start.elapsed());
+            completed_command.directory = directory;
+            // the sender is dropped when the command is cancelled
+            let cancelled = !matches!(rx.try_recv(), Err(TryRecvError::Empty));
+            let mut model = thread_model_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            model.complete_command(completed_command);
+            then(&mut model, cancelled);
+        });
+        model.mode = Mode::Executing(Execution::new(tx, progress_rx, handle));
+        if model.config.async_execution {
+            model.set_current_view_from_command(0, String::new());
+        }
+    }
+
+    /// Runs a command the user entered. A command that ends with `&` runs in the background and
+    /// the input is free again right away.
+    fn spawn_input(model_lock: &Arc<Mutex<Model>>, model: &mut Model, input: String) {
+        let options = RunOptions::new(model.output_size, model.config.stream_output);
+        let aliases = model.aliases.clone();
+        let Some(command) = background_command(&input).map(str::to_string) else {
+            spawn_command(model_lock, model, input, move |input, rx, progress| {
+                execute_command(input, &aliases, rx, &options, &progress)
+            });
+            return;
+        };
+
+        let job = model.next_job;
+        model.next_job += 1;
+        model.add_completed_command(CompletedCommand {
+            input,
+            output: Output {
+                origin: Origin::Vshell,
+                output_type: OutputType::Empty,
+            },
+            job: Some(job),
+            ..Default::default()
+        });
+        model.status = Some(Status::Info(format!("[{}] running: {}", job, command)));
+        model.mode = Mode::Idle;
+        // only `:kill` cancels a background job, ctrl+c goes to the foreground command
+        let (cancel, rx) = std::sync::mpsc::channel::<()>();
+        model.jobs.push(Job {
+            number: job,
+            input: command.clone(),
+            started: Instant::now(),
+            cancel,
+        });
+
+        let options = RunOptions {
+            stream: false,
+            background: true,
+            ..options
+        };
+        let thread_model_lock = Arc::clone(model_lock);
+        thread::spawn(move || {
+            let (progress, _) = std::sync::mpsc::channel::<Progress>();
+            let start = Instant::now();
+            let mut completed_command = without_panics(&command, || {
+                execute_command(&command, &aliases, &rx, &options, &progress)
+            });
+            completed_command.duration = Some(start.elapsed());
+            let mut model = thread_model_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            model.finish_background_job(job, completed_command);
+        });
+    }
+
+    /// Runs `inputs` one after the other on a single thread. Cancelling stops the running
+    /// command and skips the rest.
+    fn spawn_commands(model_lock: &Arc<Mutex<Model>>, model: &mut Model, inputs: Vec<String>) {
+        let options = RunOptions::new(model.output_size, model.config.stream_output);
+        let aliases = model.aliases.clone();
+        let thread_model_lock = Arc::clone(model_lock);
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (progress, progress_rx) = std::sync::mpsc::channel::<Progress>();
+        let handle = thread::spawn(move || {
+            let mut ran = 0;
+            for input in inputs.iter() {
+                let directory = std::env::current_dir().ok();
+                let start = Instant::now();
+                let mut completed_command = without_panics(input, || {
+                    execute_command(input, &aliases, &rx, &options, &progress)
+                });
+                completed_command.duration = Some(start.elapsed());
+                completed_command.directory = directory;
+                ran += 1;
+                let mut model = thread_model_lock
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                model.add_completed_command(completed_command);
+                if !matches!(rx.try_recv(), Err(TryRecvError::Empty)) {
+                    break;
                 }
             }
+            let mut model = thread_model_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if matches!(model.mode, Mode::Executing(_)) {
+                model.mode = Mode::Idle;
+            }
+            model.status = Some(Status::Info(format!(
+                "ran {} of {} commands",
+                ran,
+                inputs.len()
+            )));
+        });
+        model.mode = Mode::Executing(Execution::new(tx, progress_rx, handle));
+        if model.config.async_execution {
+            model.set_current_view_from_command(0, String::new());
         }
     }
 
-    let mut model = model_lock.lock().map_err(|_| "lock error")?;
+    let mut model = model_lock.lock()?;
+    model.status = None;
+    if let Mode::Executing(_) = model.mode {
+        if event == event::Event::CtrlC {
+            let executing_mode = mem::replace(&mut model.mode, Mode::Idle);
+            drop(model);
+            match executing_mode {
+                Mode::Executing(execution) => {
+                    // the command may have finished in the meantime, in which case there is
+                    // nobody left to cancel. dropping the sender also ends watch loops.
+                    let _ = execution.cancel.send(());
+                    drop(execution.cancel);
+                    execution
+                        .handle
+                        .join()
+                        .map_err(|_| VshellError::ThreadPanicked)?;
+                    model_lock.lock()?.restore_cancelled_input();
+                }
+                _ => unreachable!(),
+            }
+            return Ok(());
+        }
+        // with async execution the input stays editable while the command is running. events
+        // that change the mode would drop the execution and with it the way to cancel it.
+        if !model.config.async_execution
+            || matches!(
+                event,
+                event::Event::Enter | event::Event::Esc | event::Event::CtrlO
+            )
+        {
+            return Ok(());
+        }
+    }
+    if event != event::Event::Tab {
+        model.completion = None;
+    }
+    // read before the mode is borrowed mutably below
+    let picker_preview = model.config.picker_preview;
     match &mut model.mode {
-        Mode::Idle => match event {
+        Mode::Idle | Mode::Executing(_) => match event {
             event::Event::Backspace => {
                 match &mut model.current_command {
                     CurrentView::CommandWithoutOutput(command) => {
@@ -631,22 +2463,23 @@ pub(crate) fn update(
                             return Ok(());
                         }
 
-                        let thread_model_lock = Arc::clone(model_lock);
-                        let (tx, rx) = std::sync::mpsc::channel::<()>();
                         let input_string = command.input.clone();
+                        let input_string = match expand_history(&input_string, &model) {
+                            Ok(input_string) => input_string,
+                            Err(error) => {
+                                model.current_command = CurrentView::Output(Output {
+                                    origin: Origin::Vshell,
+                                    output_type: OutputType::Error(String::new(), error, None),
+                                });
+                                return Ok(());
+                            }
+                        };
 
-                        let handle = thread::spawn(move || {
-                            let completed_command = execute_command(input_string.as_str(), rx);
-                            let mut model =
-                                thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                            model.command_history.push(completed_command.clone());
-                            model.current_command =
-                                CurrentView::Output(completed_command.output.clone());
-                            model.command_history_index = model.command_history.len();
-                            model.mode = Mode::Idle;
-                            let _ = model.add_current_directory_to_history();
-                        });
-                        model.mode = Mode::Executing(true, 0, tx, handle);
+                        if is_catastrophic_rm(&input_string, &model.aliases) {
+                            model.mode = Mode::Confirm(Confirmation::dangerous(input_string));
+                            return Ok(());
+                        }
+                        spawn_input(model_lock, &mut model, input_string);
                         Ok(())
                     }
                     CurrentView::Output(_) => {
@@ -654,56 +2487,45 @@ pub(crate) fn update(
                         Ok(())
                     }
                     CurrentView::CommandWithOutput(command) => {
-                        let thread_model_lock = Arc::clone(model_lock);
-                        let (tx, rx) = std::sync::mpsc::channel::<()>();
                         let input_string = command.input.clone();
-                        let handle = thread::spawn(move || {
-                            let mut model =
-                                thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                            model.current_command =
-                                CurrentView::CommandWithoutOutput(CommandWithoutOutput {
-                                    cursor_position: input_string.len() as u64,
-                                    input: input_string.clone(),
-                                });
-                            drop(model);
-                            let completed_command = execute_command(input_string.as_str(), rx);
-                            let mut model =
-                                thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                            model.command_history.push(completed_command.clone());
-                            model.current_command =
-                                CurrentView::Output(completed_command.output.clone());
-                            model.command_history_index = model.command_history.len();
-                            model.mode = Mode::Idle;
-                            let _ = model.add_current_directory_to_history();
-                        });
-                        model.mode = Mode::Executing(true, 0, tx, handle);
+                        model.set_current_view_from_command(
+                            input_string.len() as u64,
+                            input_string.clone(),
+                        );
+                        if is_catastrophic_rm(&input_string, &model.aliases) {
+                            model.mode = Mode::Confirm(Confirmation::dangerous(input_string));
+                            return Ok(());
+                        }
+                        spawn_input(model_lock, &mut model, input_string);
                         Ok(())
                     }
                 }
             }
             event::Event::Up => {
-                if model.command_history_index > 0 {
-                    model.command_history_index -= 1;
-                    let completed_command = &model.command_history[model.command_history_index];
-                    model.current_command =
-                        CurrentView::CommandWithOutput(completed_command.clone());
+                let current_directory = std::env::current_dir().ok();
+                let previous = (0..model.command_history_index)
+                    .rev()
+                    .find(|index| model.is_listed(*index, current_directory.as_deref()));
+                if let Some(index) = previous {
+                    model.show_history_entry(index);
                 }
                 Ok(())
             }
             event::Event::Down => {
-                if !model.command_history.is_empty()
-                    && model.command_history_index < model.command_history.len() - 1
-                {
-                    model.command_history_index += 1;
-                    let completed_command = &model.command_history[model.command_history_index];
-                    model.current_command =
-                        CurrentView::CommandWithOutput(completed_command.clone());
+                let current_directory = std::env::current_dir().ok();
+                let next = (model.command_history_index + 1..model.command_history.len())
+                    .find(|index| model.is_listed(*index, current_directory.as_deref()));
+                if let Some(index) = next {
+                    model.show_history_entry(index);
                 } else {
                     model.set_current_view_from_command(0, String::new());
                 }
                 Ok(())
             }
             event::Event::Character(c) => {
+                if c == ' ' {
+                    model.expand_abbreviation();
+                }
                 match &mut model.current_command {
                     CurrentView::CommandWithoutOutput(command) => {
                         command.input.insert(command.cursor_position as usize, c);
@@ -780,15 +2602,196 @@ pub(crate) fn update(
                     }
                 }
             }
+            event::Event::CtrlW => {
+                let (input, cursor) = match &model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        (command.input.as_str(), command.cursor_position as usize)
+                    }
+                    CurrentView::CommandWithOutput(command) => {
+                        (command.input.as_str(), command.input.len())
+                    }
+                    CurrentView::Output(_) => return Ok(()),
+                };
+                let (kept, new_cursor) = delete_word_backward(input, cursor);
+                let killed = input[new_cursor..cursor].to_string();
+                model.kill_buffer = killed;
+                match &mut model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        command.input = kept;
+                        command.cursor_position = new_cursor as u64;
+                    }
+                    _ => model.set_current_view_from_command(new_cursor as u64, kept),
+                }
+                Ok(())
+            }
+            event::Event::CtrlK | event::Event::CtrlU => {
+                let (input, cursor) = match &model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        (command.input.as_str(), command.cursor_position as usize)
+                    }
+                    CurrentView::CommandWithOutput(command) => {
+                        (command.input.as_str(), command.input.len())
+                    }
+                    CurrentView::Output(_) => return Ok(()),
+                };
+                let to_end = event == event::Event::CtrlK;
+                let (kept, killed) = kill_line(input, cursor, to_end);
+                let new_cursor = match to_end {
+                    true => kept.len(),
+                    false => 0,
+                };
+                model.kill_buffer = killed;
+                match &mut model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        command.input = kept;
+                        command.cursor_position = new_cursor as u64;
+                    }
+                    _ => model.set_current_view_from_command(new_cursor as u64, kept),
+                }
+                Ok(())
+            }
+            event::Event::CtrlY => {
+                let killed = model.kill_buffer.clone();
+                paste(&killed, &mut model)
+            }
+            event::Event::AltLeft | event::Event::AltRight => {
+                let forward = event == event::Event::AltRight;
+                match &mut model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        command.cursor_position =
+                            word_jump(&command.input, command.cursor_position as usize, forward)
+                                as u64;
+                    }
+                    CurrentView::CommandWithOutput(command) => {
+                        let input = command.input.clone();
+                        let cursor = word_jump(&input, input.len(), forward);
+                        model.set_current_view_from_command(cursor as u64, input);
+                    }
+                    CurrentView::Output(_) => {}
+                }
+                Ok(())
+            }
+            event::Event::Home | event::Event::End => {
+                let to_end = event == event::Event::End;
+                match &mut model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        command.cursor_position = match to_end {
+                            true => command.input.len() as u64,
+                            false => 0,
+                        };
+                    }
+                    CurrentView::CommandWithOutput(command) => {
+                        model.current_command =
+                            CurrentView::CommandWithoutOutput(CommandWithoutOutput {
+                                cursor_position: match to_end {
+                                    true => command.input.len() as u64,
+                                    false => 0,
+                                },
+                                input: command.input.clone(),
+                            });
+                        model.command_history_index = model.command_history.len();
+                    }
+                    CurrentView::Output(_) => {}
+                }
+                Ok(())
+            }
             event::Event::Paste(text_to_insert) => paste(text_to_insert.as_str(), &mut model),
+            event::Event::Tab => {
+                let (mut input, cursor) = match &model.current_command {
+                    CurrentView::CommandWithoutOutput(command) => {
+                        (command.input.clone(), command.cursor_position as usize)
+                    }
+                    CurrentView::CommandWithOutput(command) => {
+                        (command.input.clone(), command.input.len())
+                    }
+                    CurrentView::Output(_) => return Ok(()),
+                };
+                let replacement = match &mut model.completion {
+                    // repeated presses cycle through the candidates
+                    Some(completion) => {
+                        let selected = completion
+                            .selected
+                            .map_or(0, |index| (index + 1) % completion.candidates.len());
+                        completion.selected = Some(selected);
+                        Some((
+                            completion.start,
+                            quote_completion(&completion.candidates[selected]),
+                        ))
+                    }
+                    None => {
+                        let (start, command_position) = completed_word(&input, cursor);
+                        let word = &input[start..cursor];
+                        let candidates = completion_candidates(word, command_position);
+                        match candidates.len() {
+                            0 => None,
+                            1 => {
+                                let candidate = &candidates[0];
+                                let separator = if candidate.ends_with('/') { "" } else { " " };
+                                Some((
+                                    start,
+                                    format!("{}{}", quote_completion(candidate), separator),
+                                ))
+                            }
+                            _ => {
+                                let prefix = common_prefix(&candidates).to_string();
+                                model.completion = Some(Completion {
+                                    output: Output {
+                                        origin: Origin::Vshell,
+                                        output_type: OutputType::Success(
+                                            candidates.join("\n"),
+                                            String::new(),
+                                            None,
+                                        ),
+                                    },
+                                    candidates,
+                                    selected: None,
+                                    start,
+                                });
+                                // a prefix that needs quoting can't be extended later on
+                                (prefix.len() > word.len() && quote_completion(&prefix) == prefix)
+                                    .then_some((start, prefix))
+                            }
+                        }
+                    }
+                };
+                if let Some((start, replacement)) = replacement {
+                    input.replace_range(start..cursor, &replacement);
+                    model.current_command =
+                        CurrentView::CommandWithoutOutput(CommandWithoutOutput {
+                            cursor_position: (start + replacement.len()) as u64,
+                            input,
+                        });
+                    model.command_history_index = model.command_history.len();
+                }
+                Ok(())
+            }
+            event::Event::CtrlL => {
+                model.set_current_view_from_command(0, String::new());
+                Ok(())
+            }
+            event::Event::CtrlO => {
+                if model.output_string().is_some() {
+                    model.mode = Mode::CopyWord(String::new());
+                }
+                Ok(())
+            }
+            event::Event::PageUp => {
+                model.output_scroll.page_up();
+                Ok(())
+            }
+            event::Event::PageDown => {
+                model.output_scroll.page_down();
+                Ok(())
+            }
             _ => {
                 // do nothing
                 Ok(())
             }
         },
 
-        // SAFETY: if Mode::QUIT has been set, the program will already have exited before it reaches this point
-        Mode::Quit => unreachable!(),
+        // a command run with `:qa` can finish while events are still being handled, the main
+        // loop quits right after
+        Mode::Quit => Ok(()),
         Mode::Command(command) => match event {
             event::Event::Esc => {
                 model.mode = Mode::Idle;
@@ -803,11 +2806,29 @@ pub(crate) fn update(
                 Ok(())
             }
             event::Event::Enter => {
+                let command_input = command.clone();
                 let command = Command::try_from(command.as_str());
                 if command.is_err() {
                     return Ok(());
                 }
-                let command = command.unwrap();
+                let command = match command.unwrap() {
+                    Command::Repeat => {
+                        let last_edit = model.last_edit.as_deref().map(Command::try_from);
+                        match last_edit {
+                            Some(Ok(last_edit)) => last_edit,
+                            _ => {
+                                model.mode = Mode::Idle;
+                                return Ok(());
+                            }
+                        }
+                    }
+                    command => {
+                        if matches!(command, Command::Edit(_) | Command::Replace(_)) {
+                            model.last_edit = Some(command_input);
+                        }
+                        command
+                    }
+                };
                 match command {
                     Command::Quit => {
                         model.mode = Mode::Quit;
@@ -902,46 +2923,22 @@ pub(crate) fn update(
                                             return Ok(());
                                         }
                                         let mut split_command = split_string(&command.input);
-                                        let mut current = 0;
-                                        let mut new_cursor_position = 0;
-                                        let mut indices_to_delete = Vec::new();
-                                        for (real_index, element) in
-                                            split_command.iter().enumerate()
-                                        {
-                                            match element {
-                                                StringType::Word(w) => {
-                                                    if current == beginning_index
-                                                        || current == end_index
-                                                    {
-                                                        indices_to_delete.push(real_index);
-                                                    }
-                                                    if current == end_index {
-                                                        break;
-                                                    }
-                                                    current += 1;
-                                                    if current <= beginning_index {
-                                                        new_cursor_position += w.len() as u64;
-                                                    }
-                                                }
-                                                StringType::Newline(c)
-                                                | StringType::Whitespace(c) => {
-                                                    if current <= beginning_index {
-                                                        new_cursor_position += c.len() as u64;
-                                                    }
-                                                }
-                                                StringType::Tab => {
-                                                    if current <= beginning_index {
-                                                        new_cursor_position += 1;
-                                                    }
-                                                }
+                                        let range = match hint_range(
+                                            &split_command,
+                                            beginning_index,
+                                            end_index,
+                                        ) {
+                                            Some(range) => range,
+                                            None => {
+                                                model.mode = Mode::Idle;
+                                                return Ok(());
                                             }
-                                        }
-                                        if indices_to_delete.is_empty() {
-                                            model.mode = Mode::Idle;
-                                            return Ok(());
-                                        }
-                                        split_command
-                                            .drain(indices_to_delete[0]..=indices_to_delete[1]);
+                                        };
+                                        let new_cursor_position = split_command[..*range.start()]
+                                            .iter()
+                                            .map(|s| s.as_str().len() as u64)
+                                            .sum();
+                                        split_command.drain(range);
                                         let new_command = split_command
                                             .iter()
                                             .map(|s| s.as_str())
@@ -1006,25 +3003,45 @@ pub(crate) fn update(
                                 let number = number.unwrap();
                                 if number < model.directory_history.len() {
                                     let index = model.directory_history.len() - number - 1;
-                                    let directory = &model.directory_history[index];
-                                    let new_command =
-                                        format!("cd \"{}\"", directory.to_string_lossy());
+                                    let new_command = cd_command(&model.directory_history[index]);
                                     let (_, rx) = std::sync::mpsc::channel::<()>(); // intentionally unused receiver
-                                    let completed_command =
-                                        execute_command(new_command.as_str(), rx);
+                                    let (progress, _) = std::sync::mpsc::channel::<Progress>(); // cd runs synchronously
+                                    let completed_command = execute_command(
+                                        new_command.as_str(),
+                                        &BTreeMap::new(),
+                                        &rx,
+                                        &RunOptions::new(model.output_size, false),
+                                        &progress,
+                                    );
                                     if model.add_current_directory_to_history().is_err() {
                                         return Ok(());
                                     }
-                                    model.command_history.push(completed_command.clone());
+                                    model.push_to_history(completed_command.clone());
                                     model.command_history_index = model.command_history.len();
                                     model.current_command =
                                         CurrentView::Output(completed_command.output.clone());
                                 }
                             }
+                            // jobs are killed with `:kill` instead
+                            crate::HistoryType::Jobs => {}
                         }
                         model.mode = Mode::Idle;
                         Ok(())
                     }
+                    Command::SelectCommandNumber(number) => {
+                        model.mode = Mode::Idle;
+                        match model.history_index_of_number(number) {
+                            Some(index) => {
+                                let input = model.command_history[index].input.clone();
+                                model.set_current_view_from_command(input.len() as u64, input);
+                            }
+                            None => {
+                                model.status =
+                                    Some(Status::Error(format!("no command number {}", number)))
+                            }
+                        }
+                        Ok(())
+                    }
                     Command::JumpBefore(hint) => {
                         match &model.current_command {
                             CurrentView::CommandWithOutput(c) => {
@@ -1049,7 +3066,7 @@ pub(crate) fn update(
                             return Ok(());
                         }
                         // we only accept digits so this must be a valid usize (unless it's too large, that is acceptable)
-                        let index = base26_to_base10(&hint)?;
+                        let index = base26_to_base10(&hint).map_err(VshellError::InvalidHint)?;
                         model.mode = Mode::Idle;
                         // SAFETY: Jumping Modes can only be entered if command has an input string
                         let split_command =
@@ -1103,7 +3120,7 @@ pub(crate) fn update(
                             model.mode = Mode::Idle;
                             return Ok(());
                         }
-                        let index = base26_to_base10(&hint)?;
+                        let index = base26_to_base10(&hint).map_err(VshellError::InvalidHint)?;
                         model.mode = Mode::Idle;
                         // SAFETY: Jumping Modes can only be entered if command has an input string
                         let split_command =
@@ -1136,6 +3153,24 @@ pub(crate) fn update(
                         }
                         Ok(())
                     }
+                    Command::GotoChar(position) => {
+                        model.mode = Mode::Idle;
+                        match &model.current_command {
+                            CurrentView::CommandWithOutput(c) => {
+                                let command = c.input.clone();
+                                model.set_current_view_from_command(command.len() as u64, command);
+                            }
+                            CurrentView::Output(_) => return Ok(()),
+                            _ => {}
+                        }
+                        if let CurrentView::CommandWithoutOutput(command) =
+                            &mut model.current_command
+                        {
+                            command.cursor_position =
+                                char_position(&command.input, position) as u64;
+                        }
+                        Ok(())
+                    }
                     Command::Pin => {
                         model.mode = Mode::Idle;
                         match &model.current_command {
@@ -1190,71 +3225,43 @@ pub(crate) fn update(
                     }
                     Command::CopyOutput(copy_output) => {
                         model.mode = Mode::Idle;
-                        let output_string = match model.current_command {
-                            CurrentView::CommandWithoutOutput(_) => {
-                                // do nothing
+                        let output_string = match model.output_string() {
+                            Some(output_string) => output_string,
+                            None => return Ok(()),
+                        };
+                        match copied_text(&output_string, copy_output) {
+                            Ok(Some(text)) => clipboard.set_text(text),
+                            Ok(None) => {}
+                            Err(e) => {
+                                model.status = Some(Status::Error(format!("invalid hint: {}", e)))
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::CopyHistoryOutput(number, copy_output) => {
+                        model.mode = Mode::Idle;
+                        let output_string = match model.history_entry(number) {
+                            Some(HistoryEntry::Command(index)) => {
+                                ansi::strip(&model.command_history[index].output.to_string())
+                            }
+                            Some(HistoryEntry::Pinned(_)) => {
+                                model.status = Some(Status::Error(format!(
+                                    "{} is a pinned command without output",
+                                    number
+                                )));
                                 return Ok(());
                             }
-                            CurrentView::CommandWithOutput(ref command) => {
-                                command.output.to_string()
+                            None => {
+                                model.status =
+                                    Some(Status::Error(format!("no history entry {}", number)));
+                                return Ok(());
                             }
-                            CurrentView::Output(ref command) => command.to_string(),
                         };
-                        match copy_output {
-                            CopyOutput::All => clipboard.set_text(output_string)?,
-                            CopyOutput::Single(hint) => {
-                                let index = base26_to_base10(&hint)?;
-                                let split_output = split_string(&output_string);
-                                let mut current = 0;
-                                let mut new_output = String::new();
-                                for element in split_output.iter() {
-                                    match element {
-                                        StringType::Word(w) => {
-                                            if current == index {
-                                                new_output.push_str(w);
-                                                break;
-                                            }
-                                            current += 1;
-                                        }
-                                        StringType::Newline(c) | StringType::Whitespace(c) => {
-                                            new_output.push_str(c);
-                                        }
-                                        StringType::Tab => {
-                                            new_output.push('\t');
-                                        }
-                                    }
-                                }
-                                clipboard.set_text(new_output)?;
-                            }
-                            CopyOutput::Range(beginning, end) => {
-                                let beginning_index = base26_to_base10(&beginning)?;
-                                let end_index = base26_to_base10(&end)?;
-                                if end_index < beginning_index {
-                                    return Ok(());
-                                }
-                                let split_output = split_string(&output_string);
-                                let mut current = 0;
-                                let mut new_output = String::new();
-                                for element in split_output.iter() {
-                                    match element {
-                                        StringType::Word(w) => {
-                                            if current >= beginning_index && current <= end_index {
-                                                new_output.push_str(w);
-                                            }
-                                            if current == end_index {
-                                                break;
-                                            }
-                                            current += 1;
-                                        }
-                                        StringType::Newline(c) | StringType::Whitespace(c) => {
-                                            new_output.push_str(c);
-                                        }
-                                        StringType::Tab => {
-                                            new_output.push('\t');
-                                        }
-                                    }
-                                }
-                                clipboard.set_text(new_output)?;
+                        match copied_text(&output_string, copy_output) {
+                            Ok(Some(text)) => clipboard.set_text(text),
+                            Ok(None) => {}
+                            Err(e) => {
+                                model.status = Some(Status::Error(format!("invalid hint: {}", e)))
                             }
                         }
                         Ok(())
@@ -1267,61 +3274,808 @@ pub(crate) fn update(
                         model.mode = Mode::Idle;
                         Ok(())
                     }
-                    Command::Paste => {
-                        model.mode = Mode::Idle;
-                        paste(clipboard.get_text()?.as_str(), &mut model)
-                    }
-                    Command::ShellExecute(shell, prefix) => {
-                        fn execute_shell_command(
-                            shell: &str,
-                            command: &str,
-                            prefix: Option<String>,
-                            receiver: Receiver<()>,
-                        ) -> CompletedCommand {
-                            let command = match prefix {
-                                None => command.to_string(),
-                                Some(mut prefix) => {
-                                    prefix.push_str(command);
-                                    prefix
+                    Command::Watch(interval, input) => {
+                        let options =
+                            RunOptions::new(model.output_size, model.config.stream_output);
+                        let aliases = model.aliases.clone();
+                        let thread_model_lock = Arc::clone(model_lock);
+                        let (tx, rx) = std::sync::mpsc::channel::<()>();
+                        let (progress, progress_rx) = std::sync::mpsc::channel::<Progress>();
+                        let handle = thread::spawn(move || {
+                            let completed_command = loop {
+                                let start = Instant::now();
+                                let mut completed_command = without_panics(&input, || {
+                                    execute_command(&input, &aliases, &rx, &options, &progress)
+                                });
+                                completed_command.duration = Some(start.elapsed());
+                                let mut model = thread_model_lock
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner);
+                                if model.output_replaces_current_view() {
+                                    model.current_command =
+                                        CurrentView::Output(completed_command.output.clone());
                                 }
-                            };
-
-                            let executed_command = std::process::Command::new(shell)
-                                .arg("-c")
-                                .arg(&command)
-                                .stdout(Stdio::piped())
-                                .stderr(Stdio::piped())
-                                .spawn();
-
-                            match executed_command {
-                                Err(e) => {
-                                    CompletedCommand {
-                                        input: command.to_string(),
-                                        output: Output {
-                                            origin: Origin::Other(shell.to_string()),
-                                            output_type: OutputType::Error(
-                                                String::new(),
-                                                format!("Could not spawn process: {}", e),
-                                            ),
-                                        },
-                                    }
+                                drop(model);
+                                // anything but a timeout means the watch was cancelled
+                                if !matches!(
+                                    rx.recv_timeout(interval),
+                                    Err(RecvTimeoutError::Timeout)
+                                ) {
+                                    break completed_command;
                                 }
-                                Ok(mut executed_command) => {
-                                    loop {
-                                        if executed_command.try_wait().is_err()
-                                            || (executed_command.try_wait().is_ok()
-                                                && executed_command.try_wait().unwrap().is_some())
-                                        {
-                                            break;
-                                        }
-
-                                        if receiver.try_recv().is_ok() {
-                                            let result = executed_command.kill();
-
-                                            if let Err(e) = result {
-                                                return CompletedCommand {
-                                                    input: command.to_string(),
-                                                    output: Output {
+                            };
+                            let mut model = thread_model_lock
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner);
+                            model.complete_command(completed_command);
+                        });
+                        model.mode = Mode::Executing(Execution::new(tx, progress_rx, handle));
+                        if model.config.async_execution {
+                            model.set_current_view_from_command(0, String::new());
+                        }
+                        Ok(())
+                    }
+                    Command::Top => {
+                        model.output_scroll.top();
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::Bottom => {
+                        model.output_scroll.bottom();
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::ToggleHidden(number) => {
+                        model.mode = Mode::Idle;
+                        if let Some(HistoryEntry::Command(index)) = model.history_entry(number) {
+                            model.command_history[index].hidden =
+                                !model.command_history[index].hidden;
+                        }
+                        Ok(())
+                    }
+                    Command::Set(key, value) => {
+                        model.mode = Mode::Idle;
+                        model.status = Some(match model.config.set(&key, &value) {
+                            Ok(()) => Status::Info(format!("{} = {}", key, value)),
+                            Err(e) => Status::Error(e),
+                        });
+                        Ok(())
+                    }
+                    Command::CopyHistory(with_outputs) => {
+                        model.mode = Mode::Idle;
+                        clipboard.set_text(history_text(&model.command_history, with_outputs));
+                        model.status = Some(Status::Info(format!(
+                            "copied {} commands",
+                            model.command_history.len()
+                        )));
+                        Ok(())
+                    }
+                    Command::PasteLines => {
+                        model.mode = Mode::Idle;
+                        let inputs = pasted_commands(&clipboard.get_text());
+                        if inputs.is_empty() {
+                            model.status = Some(Status::Error("no commands to paste".to_string()));
+                            return Ok(());
+                        }
+                        let dangerous = inputs
+                            .iter()
+                            .any(|input| is_catastrophic_rm(input, &model.aliases));
+                        if dangerous || inputs.len() > PASTE_LINES_LIMIT {
+                            model.mode =
+                                Mode::Confirm(Confirmation::pasted_commands(inputs, dangerous));
+                            return Ok(());
+                        }
+                        spawn_commands(model_lock, &mut model, inputs);
+                        Ok(())
+                    }
+                    Command::SearchHistory(query) => {
+                        model.mode = Mode::Idle;
+                        match model.search_history(&query, model.command_history.len()) {
+                            Some(matched) => {
+                                model.show_history_entry(matched);
+                                model.mode = Mode::Search(HistorySearch { query, matched });
+                            }
+                            None => {
+                                model.status =
+                                    Some(Status::Error(format!("no command matches {}", query)))
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::FindInOutput(query) => {
+                        model.mode = Mode::Idle;
+                        if model.output_string().is_none() {
+                            model.status = Some(Status::Error("no output to search".to_string()));
+                            return Ok(());
+                        }
+                        match model.find_in_output(&query, 0) {
+                            Some(matched) => {
+                                model.mode = Mode::Find(OutputSearch { query, matched });
+                            }
+                            None => {
+                                model.status =
+                                    Some(Status::Error(format!("no word matches {}", query)))
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::Sleep(duration) => {
+                        // recorded as typed so running it again from the history sleeps in vshell
+                        let input = format!(":sleep:{}", duration.as_secs_f64());
+                        spawn_command(model_lock, &mut model, input, move |input, rx, progress| {
+                            execute_sleep(input, duration, rx, &progress)
+                        });
+                        Ok(())
+                    }
+                    Command::Version(program) => {
+                        let options = RunOptions::new(model.output_size, false);
+                        let program = shlex::quote(&program).to_string();
+                        let input = format!("{} {}", program, VERSION_ARGUMENTS[0]);
+                        spawn_command(model_lock, &mut model, input, move |_, rx, progress| {
+                            for argument in VERSION_ARGUMENTS {
+                                let input = format!("{} {}", program, argument);
+                                let completed_command = execute_command(
+                                    &input,
+                                    &BTreeMap::new(),
+                                    rx,
+                                    &options,
+                                    &progress,
+                                );
+                                let cancelled = !matches!(rx.try_recv(), Err(TryRecvError::Empty));
+                                if cancelled
+                                    || matches!(
+                                        completed_command.output.output_type,
+                                        OutputType::Success(..)
+                                    )
+                                {
+                                    return completed_command;
+                                }
+                            }
+                            CompletedCommand {
+                                input: format!("{} {}", program, VERSION_ARGUMENTS[0]),
+                                output: Output {
+                                    origin: Origin::Vshell,
+                                    output_type: OutputType::Error(
+                                        String::new(),
+                                        format!(
+                                            "{}: none of {} worked",
+                                            program,
+                                            VERSION_ARGUMENTS.join(", ")
+                                        ),
+                                        None,
+                                    ),
+                                },
+                                ..Default::default()
+                            }
+                        });
+                        Ok(())
+                    }
+                    Command::Everywhere(input) => {
+                        let options = RunOptions::new(model.output_size, false);
+                        let aliases = model.aliases.clone();
+                        let directories = unique_directories(&model.directory_history);
+                        spawn_command(model_lock, &mut model, input, move |input, rx, progress| {
+                            let mut combined = String::new();
+                            let mut failed = false;
+                            for directory in directories {
+                                if !matches!(rx.try_recv(), Err(TryRecvError::Empty)) {
+                                    combined.push_str("cancelled\n");
+                                    failed = true;
+                                    break;
+                                }
+                                combined.push_str(&format!("==> {} <==\n", directory.display()));
+                                let options = RunOptions {
+                                    directory: Some(directory),
+                                    ..options.clone()
+                                };
+                                let completed_command =
+                                    execute_command(input, &aliases, rx, &options, &progress);
+                                let output = completed_command.output.to_string();
+                                combined.push_str(&output);
+                                if !output.is_empty() && !output.ends_with('\n') {
+                                    combined.push('\n');
+                                }
+                                combined.push('\n');
+                                failed |= matches!(
+                                    completed_command.output.output_type,
+                                    OutputType::Error(..)
+                                );
+                            }
+                            let output_type = match failed {
+                                true => OutputType::Error(combined, String::new(), None),
+                                false => OutputType::Success(combined, String::new(), Some(0)),
+                            };
+                            CompletedCommand {
+                                input: input.to_string(),
+                                output: Output {
+                                    origin: Origin::Vshell,
+                                    output_type,
+                                },
+                                ..Default::default()
+                            }
+                        });
+                        Ok(())
+                    }
+                    Command::DeleteHistoryEntry(number) => {
+                        model.mode = Mode::Idle;
+                        if let Some(HistoryEntry::Command(index)) = model.history_entry(number) {
+                            match model.config.confirm_destructive {
+                                true => {
+                                    model.mode = Mode::Confirm(Confirmation::delete_history_entry(
+                                        index,
+                                        &model.command_history[index].input,
+                                    ))
+                                }
+                                false => model.delete_history_entry(index),
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::RunAndPin => {
+                        model.mode = Mode::Idle;
+                        let input = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(command) => command.input.clone(),
+                            CurrentView::CommandWithOutput(command) => command.input.clone(),
+                            CurrentView::Output(_) => return Ok(()),
+                        };
+                        if input.is_empty() {
+                            return Ok(());
+                        }
+                        // pinned as typed, before `!!` is expanded
+                        if !model
+                            .pinned_commands
+                            .iter()
+                            .any(|pinned_command| pinned_command.input == input)
+                        {
+                            model.pinned_commands.push(CommandWithoutOutput {
+                                cursor_position: input.len() as u64,
+                                input,
+                            });
+                        }
+                        drop(model);
+                        update(model_lock, event::Event::Enter, clipboard)
+                    }
+                    Command::QuitAfter(input) => {
+                        let options =
+                            RunOptions::new(model.output_size, model.config.stream_output);
+                        let aliases = model.aliases.clone();
+                        spawn_command_then(
+                            model_lock,
+                            &mut model,
+                            input,
+                            move |input, rx, progress| {
+                                execute_command(input, &aliases, rx, &options, &progress)
+                            },
+                            |model, cancelled| {
+                                // the main loop sees this and quits the way `:q` does
+                                if !cancelled {
+                                    model.mode = Mode::Quit;
+                                }
+                            },
+                        );
+                        Ok(())
+                    }
+                    Command::ToggleTable => {
+                        model.mode = Mode::Idle;
+                        if model.output_string().is_some() {
+                            model.table_view = !model.table_view;
+                            model.output_scroll.reset();
+                        }
+                        Ok(())
+                    }
+                    Command::DedupeHistory => {
+                        model.mode = Mode::Idle;
+                        let removed = model.dedupe_history();
+                        if model.status.is_none() {
+                            model.status =
+                                Some(Status::Info(format!("removed {} duplicates", removed)));
+                        }
+                        Ok(())
+                    }
+                    Command::ClearHistory(pins) => {
+                        match model.config.confirm_destructive {
+                            true => model.mode = Mode::Confirm(Confirmation::clear_history(pins)),
+                            false => {
+                                model.mode = Mode::Idle;
+                                model.clear_history(pins);
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::Abbreviate(key, expansion) => {
+                        model.mode = Mode::Idle;
+                        model.status = Some(Status::Info(match expansion.is_empty() {
+                            true => format!("removed abbreviation {}", key),
+                            false => format!("{} expands to {}", key, expansion),
+                        }));
+                        match expansion.is_empty() {
+                            true => model.config.abbreviations.remove(&key),
+                            false => model.config.abbreviations.insert(key, expansion),
+                        };
+                        Ok(())
+                    }
+                    Command::Alias(name, expansion) => {
+                        model.mode = Mode::Idle;
+                        model.status = Some(Status::Info(match expansion.is_empty() {
+                            true => format!("removed alias {}", name),
+                            false => format!("{} runs {}", name, expansion),
+                        }));
+                        match expansion.is_empty() {
+                            true => model.aliases.remove(&name),
+                            false => model.aliases.insert(name, expansion),
+                        };
+                        Ok(())
+                    }
+                    Command::ShowAliases => {
+                        model.mode = Mode::Idle;
+                        let aliases = model
+                            .aliases
+                            .iter()
+                            .map(|(name, expansion)| format!("{}\t{}", name, expansion))
+                            .collect::<Vec<String>>()
+                            .join("\n");
+                        model.remember_output_scroll();
+                        model.current_command = CurrentView::Output(Output {
+                            origin: Origin::Vshell,
+                            output_type: OutputType::Success(aliases, String::new(), None),
+                        });
+                        model.command_history_index = model.command_history.len();
+                        model.output_scroll.reset();
+                        Ok(())
+                    }
+                    Command::Replay(amount) => {
+                        model.mode = Mode::Idle;
+                        // cloned since replaying adds to the history
+                        let inputs = model
+                            .command_history
+                            .iter()
+                            .rev()
+                            .take(amount)
+                            .rev()
+                            .map(|command| command.input.clone())
+                            .collect::<Vec<String>>();
+                        if inputs.is_empty() {
+                            return Ok(());
+                        }
+                        spawn_commands(model_lock, &mut model, inputs);
+                        Ok(())
+                    }
+                    Command::LastDirectory => {
+                        model.mode = Mode::Idle;
+                        let Ok(current_directory) = std::env::current_dir() else {
+                            return Ok(());
+                        };
+                        let Some(new_command) = model
+                            .last_directory(&current_directory)
+                            .map(|directory| cd_command(directory))
+                        else {
+                            return Ok(());
+                        };
+                        let (_, rx) = std::sync::mpsc::channel::<()>(); // intentionally unused receiver
+                        let (progress, _) = std::sync::mpsc::channel::<Progress>(); // cd runs synchronously
+                        let mut completed_command = execute_command(
+                            new_command.as_str(),
+                            &BTreeMap::new(),
+                            &rx,
+                            &RunOptions::new(model.output_size, false),
+                            &progress,
+                        );
+                        // where the command was run from, not where it went
+                        completed_command.directory = Some(current_directory);
+                        model.add_completed_command(completed_command);
+                        Ok(())
+                    }
+                    Command::DefineTemplate(name, template) => {
+                        model.mode = Mode::Idle;
+                        model.status = Some(Status::Info(format!("defined template {}", name)));
+                        model.templates.insert(name, template);
+                        Ok(())
+                    }
+                    Command::Template(name, args) => {
+                        model.mode = Mode::Idle;
+                        let filled = match model.templates.get(&name) {
+                            Some(template) => fill_template(template, &args),
+                            None => Err(format!("no template named {}", name)),
+                        };
+                        match filled {
+                            Ok(filled) => {
+                                model.set_current_view_from_command(filled.len() as u64, filled)
+                            }
+                            Err(e) => model.status = Some(Status::Error(e)),
+                        }
+                        Ok(())
+                    }
+                    Command::Pipe(input) => {
+                        let Some(output) = model.output_string() else {
+                            model.mode = Mode::Idle;
+                            model.status = Some(Status::Error("no output to pipe".to_string()));
+                            return Ok(());
+                        };
+                        let options =
+                            RunOptions::new(model.output_size, model.config.stream_output);
+                        spawn_command(model_lock, &mut model, input, move |input, rx, progress| {
+                            let _ = progress.send(Progress::Phase(Phase::Parsing));
+                            let stages =
+                                split_pipeline(&expand_globs(&expand_variables(input), None))
+                                    .and_then(|stages| match stages.len() {
+                                        1 => Ok(stages),
+                                        _ => Err("pipe: only a single command can be piped to"
+                                            .to_string()),
+                                    });
+                            match stages {
+                                Ok(stages) => execute_program(
+                                    input,
+                                    &stages[0],
+                                    Some(output),
+                                    rx,
+                                    &options,
+                                    &progress,
+                                ),
+                                Err(e) => CompletedCommand {
+                                    input: input.to_string(),
+                                    output: Output {
+                                        origin: Origin::Vshell,
+                                        output_type: OutputType::Error(String::new(), e, Some(2)),
+                                    },
+                                    ..Default::default()
+                                },
+                            }
+                        });
+                        Ok(())
+                    }
+                    Command::Buffer(Buffer::Save(name)) => {
+                        model.mode = Mode::Idle;
+                        let output = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(_) => None,
+                            CurrentView::CommandWithOutput(command) => Some(&command.output),
+                            CurrentView::Output(output) => Some(output),
+                        };
+                        match output.cloned() {
+                            Some(output) => {
+                                model.status =
+                                    Some(Status::Info(format!("saved output as {}", name)));
+                                model.buffers.insert(name, output);
+                            }
+                            None => {
+                                model.status = Some(Status::Error("no output to save".to_string()))
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::Buffer(Buffer::Show(name)) => {
+                        model.mode = Mode::Idle;
+                        match model.buffers.get(&name).cloned() {
+                            Some(output) => {
+                                model.remember_output_scroll();
+                                model.current_command = CurrentView::Output(output);
+                                model.command_history_index = model.command_history.len();
+                                model.output_scroll.reset();
+                                model.table_view = false;
+                            }
+                            None => {
+                                model.status =
+                                    Some(Status::Error(format!("no buffer named {}", name)))
+                            }
+                        }
+                        Ok(())
+                    }
+                    Command::Record(Record::Start(path)) => {
+                        model.mode = Mode::Idle;
+                        if model.recording.is_some() {
+                            model.status = Some(Status::Error("already recording".to_string()));
+                            return Ok(());
+                        }
+                        let path = path.unwrap_or_else(|| {
+                            let seconds = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|duration| duration.as_secs())
+                                .unwrap_or_default();
+                            PathBuf::from(format!("vshell-transcript-{}.txt", seconds))
+                        });
+                        let file = std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path);
+                        model.status = Some(match file {
+                            Ok(file) => {
+                                model.recording = Some(file);
+                                Status::Info(format!("recording to {}", path.to_string_lossy()))
+                            }
+                            Err(e) => Status::Error(format!(
+                                "could not record to {}: {}",
+                                path.to_string_lossy(),
+                                e
+                            )),
+                        });
+                        Ok(())
+                    }
+                    Command::Record(Record::Stop) => {
+                        model.mode = Mode::Idle;
+                        model.status = Some(match model.recording.take() {
+                            Some(_) => Status::Info("recording stopped".to_string()),
+                            None => Status::Error("not recording".to_string()),
+                        });
+                        Ok(())
+                    }
+                    Command::TogglePasteGuard => {
+                        model.config.paste_guard = !model.config.paste_guard;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::ToggleHistoryNumbers => {
+                        model.config.history_numbers = !model.config.history_numbers;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::InsertDate => {
+                        model.mode = Mode::Idle;
+                        let date = crate::format_timestamp(SystemTime::now());
+                        paste(&shlex::quote(&date), &mut model)
+                    }
+                    Command::InsertDirectory => {
+                        model.mode = Mode::Idle;
+                        let current_directory = match std::env::current_dir() {
+                            Ok(current_directory) => current_directory,
+                            Err(e) => {
+                                model.status =
+                                    Some(Status::Error(format!("no current directory: {}", e)));
+                                return Ok(());
+                            }
+                        };
+                        paste(
+                            &shlex::quote(&current_directory.to_string_lossy()),
+                            &mut model,
+                        )
+                    }
+                    Command::ToggleStream => {
+                        model.config.stream_output = !model.config.stream_output;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::Tokens => {
+                        model.mode = Mode::Idle;
+                        let input = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(command) => &command.input,
+                            CurrentView::CommandWithOutput(command) => &command.input,
+                            CurrentView::Output(_) => return Ok(()),
+                        };
+                        let output_type = match shlex::split(input) {
+                            Some(tokens) => OutputType::Success(
+                                tokens
+                                    .iter()
+                                    .map(|token| format!("{:?}\n", token))
+                                    .collect(),
+                                String::new(),
+                                None,
+                            ),
+                            None => OutputType::Error(
+                                String::new(),
+                                "tokens: unbalanced quotes or trailing escape".to_string(),
+                                None,
+                            ),
+                        };
+                        let input = input.clone();
+                        model.current_command = CurrentView::CommandWithOutput(CompletedCommand {
+                            input,
+                            output: Output {
+                                origin: Origin::Vshell,
+                                output_type,
+                            },
+                            ..Default::default()
+                        });
+                        model.output_scroll.reset();
+                        Ok(())
+                    }
+                    Command::HistoryHere => {
+                        model.history_here = !model.history_here;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::ShowHidden => {
+                        model.config.show_hidden = !model.config.show_hidden;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::Grep(pattern, invert) => {
+                        model.mode = Mode::Idle;
+                        let output = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(_) => return Ok(()),
+                            CurrentView::CommandWithOutput(command) => &command.output,
+                            CurrentView::Output(output) => output,
+                        };
+                        let filtered = output.map_text(|text| grep_lines(text, &pattern, invert));
+                        model.current_command = CurrentView::Output(filtered);
+                        model.output_scroll.reset();
+                        Ok(())
+                    }
+                    Command::Columns(first, last) => {
+                        model.mode = Mode::Idle;
+                        let output = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(_) => return Ok(()),
+                            CurrentView::CommandWithOutput(command) => &command.output,
+                            CurrentView::Output(output) => output,
+                        };
+                        let selected = output.map_text(|text| select_columns(text, first, last));
+                        model.current_command = CurrentView::Output(selected);
+                        model.output_scroll.reset();
+                        Ok(())
+                    }
+                    Command::Align => {
+                        model.mode = Mode::Idle;
+                        let output = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(_) => return Ok(()),
+                            CurrentView::CommandWithOutput(command) => &command.output,
+                            CurrentView::Output(output) => output,
+                        };
+                        let aligned = output.map_text(align_pairs);
+                        model.current_command = CurrentView::Output(aligned);
+                        model.output_scroll.reset();
+                        Ok(())
+                    }
+                    Command::RunRange(beginning, end) => {
+                        model.mode = Mode::Idle;
+                        let input = match &model.current_command {
+                            CurrentView::CommandWithoutOutput(command) => command.input.clone(),
+                            CurrentView::CommandWithOutput(command) => command.input.clone(),
+                            CurrentView::Output(_) => return Ok(()),
+                        };
+                        let (beginning, end) =
+                            match (base26_to_base10(&beginning), base26_to_base10(&end)) {
+                                (Ok(beginning), Ok(end)) => (beginning, end),
+                                _ => return Ok(()),
+                            };
+                        let split_input = split_string(&input);
+                        let range_input = match hint_range(&split_input, beginning, end) {
+                            Some(range) => split_input[range]
+                                .iter()
+                                .map(|s| s.as_str())
+                                .collect::<String>(),
+                            None => return Ok(()),
+                        };
+                        if has_open_quote(&range_input).is_some() {
+                            return Ok(());
+                        }
+                        let options =
+                            RunOptions::new(model.output_size, model.config.stream_output);
+                        let aliases = model.aliases.clone();
+                        let line = input.clone();
+                        spawn_command_then(
+                            model_lock,
+                            &mut model,
+                            range_input,
+                            move |input, rx, progress| {
+                                execute_command(input, &aliases, rx, &options, &progress)
+                            },
+                            |model, cancelled| {
+                                // keep the whole line so the other parts can be run as well. the
+                                // range is in the history on its own.
+                                if let (false, CurrentView::Output(output)) =
+                                    (cancelled, &model.current_command)
+                                {
+                                    model.current_command =
+                                        CurrentView::CommandWithOutput(CompletedCommand {
+                                            input: line,
+                                            output: output.clone(),
+                                            ..Default::default()
+                                        });
+                                }
+                            },
+                        );
+                        if model.config.async_execution {
+                            model.set_current_view_from_command(input.len() as u64, input);
+                        }
+                        Ok(())
+                    }
+                    Command::CopyWord => {
+                        model.mode = match model.output_string() {
+                            Some(_) => Mode::CopyWord(String::new()),
+                            None => Mode::Idle,
+                        };
+                        Ok(())
+                    }
+                    Command::SetDirectoryDisplay(directory_display) => {
+                        model.config.directory_display = directory_display;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::ExpandPins => {
+                        model.mode = Mode::Idle;
+                        if model.pinned_commands.is_empty() {
+                            return Ok(());
+                        }
+                        let script = model
+                            .pinned_commands
+                            .iter()
+                            .map(|command| command.input.as_str())
+                            .collect::<Vec<&str>>()
+                            // a newline would only separate words once the input is run
+                            .join("; ");
+                        model.set_current_view_from_command(script.len() as u64, script);
+                        Ok(())
+                    }
+                    Command::TogglePreview => {
+                        model.config.picker_preview = !model.config.picker_preview;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::ToggleAsync => {
+                        model.config.async_execution = !model.config.async_execution;
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::Paste => {
+                        model.mode = Mode::Idle;
+                        paste(clipboard.get_text().as_str(), &mut model)
+                    }
+                    Command::ShellExecute(shell, prefix) => {
+                        fn execute_shell_command(
+                            shell: &str,
+                            command: &str,
+                            prefix: Option<String>,
+                            receiver: &Receiver<()>,
+                            options: &RunOptions,
+                            progress: Sender<Progress>,
+                        ) -> CompletedCommand {
+                            let command = match prefix {
+                                None => command.to_string(),
+                                Some(mut prefix) => {
+                                    prefix.push_str(command);
+                                    prefix
+                                }
+                            };
+
+                            let _ = progress.send(Progress::Phase(Phase::Spawning));
+                            let executed_command = std::process::Command::new(shell)
+                                .arg("-c")
+                                .arg(&command)
+                                .envs(
+                                    (options.size.columns > 0)
+                                        .then(|| {
+                                            [
+                                                ("COLUMNS", options.size.columns.to_string()),
+                                                ("LINES", options.size.lines.to_string()),
+                                            ]
+                                        })
+                                        .into_iter()
+                                        .flatten(),
+                                )
+                                .stdout(Stdio::piped())
+                                .stderr(Stdio::piped())
+                                .spawn();
+
+                            match executed_command {
+                                Err(e) => CompletedCommand {
+                                    input: command.to_string(),
+                                    output: Output {
+                                        origin: Origin::Other(shell.to_string()),
+                                        output_type: OutputType::Error(
+                                            String::new(),
+                                            format!("Could not spawn process: {}", e),
+                                            None,
+                                        ),
+                                    },
+                                    ..Default::default()
+                                },
+                                Ok(mut executed_command) => {
+                                    let _ = progress.send(Progress::Phase(Phase::Running));
+                                    let origin = Origin::Other(shell.to_string());
+                                    let mut streamed_output =
+                                        StreamedOutput::new(&mut executed_command);
+                                    loop {
+                                        if executed_command.try_wait().is_err()
+                                            || (executed_command.try_wait().is_ok()
+                                                && executed_command.try_wait().unwrap().is_some())
+                                        {
+                                            break;
+                                        }
+
+                                        if receiver.recv_timeout(OUTPUT_POLL_INTERVAL).is_ok() {
+                                            let result = executed_command.kill();
+
+                                            if let Err(e) = result {
+                                                return CompletedCommand {
+                                                    input: command.to_string(),
+                                                    output: Output {
                                                         origin: Origin::Other(shell.to_string()),
                                                         output_type: OutputType::Error(
                                                             String::new(),
@@ -1329,16 +4083,22 @@ pub(crate) fn update(
                                                                 "Could not kill process: {}",
                                                                 e
                                                             ),
+                                                            None,
                                                         ),
                                                     },
+                                                    ..Default::default()
                                                 };
                                             }
 
                                             break;
                                         }
+                                        if options.stream {
+                                            streamed_output.report(&origin, &progress);
+                                        }
                                     }
 
-                                    let executed_command = executed_command.wait_with_output();
+                                    let executed_command =
+                                        streamed_output.finish(executed_command.wait());
 
                                     CompletedCommand::new(
                                         command.to_string(),
@@ -1350,6 +4110,9 @@ pub(crate) fn update(
                         }
 
                         model.mode = Mode::Idle;
+                        let options =
+                            RunOptions::new(model.output_size, model.config.stream_output);
+                        let shell = shell.unwrap_or_else(|| model.config.shell.clone());
                         match &mut model.current_command {
                             CurrentView::CommandWithoutOutput(command) => {
                                 if command.input.is_empty() {
@@ -1367,49 +4130,34 @@ pub(crate) fn update(
                                     return Ok(());
                                 }
 
-                                let thread_model_lock = Arc::clone(model_lock);
-                                let (tx, rx) = std::sync::mpsc::channel::<()>();
                                 let input_string = command.input.clone();
-
-                                let handle = thread::spawn(move || {
-                                    let completed_command =
-                                        execute_shell_command(&shell, &input_string, prefix, rx);
-                                    let mut model =
-                                        thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                                    model.command_history.push(completed_command.clone());
-                                    model.current_command =
-                                        CurrentView::Output(completed_command.output.clone());
-                                    model.command_history_index = model.command_history.len();
-                                    let _ = model.add_current_directory_to_history();
-                                    model.mode = Mode::Idle;
-                                });
-                                model.mode = Mode::Executing(true, 0, tx, handle);
+                                spawn_command(
+                                    model_lock,
+                                    &mut model,
+                                    input_string,
+                                    move |input, rx, progress| {
+                                        execute_shell_command(
+                                            &shell, input, prefix, rx, &options, progress,
+                                        )
+                                    },
+                                );
                             }
                             CurrentView::CommandWithOutput(command) => {
-                                let thread_model_lock = Arc::clone(model_lock);
-                                let (tx, rx) = std::sync::mpsc::channel::<()>();
                                 let input_string = command.input.clone();
-                                let handle = thread::spawn(move || {
-                                    let mut model =
-                                        thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                                    model.current_command =
-                                        CurrentView::CommandWithoutOutput(CommandWithoutOutput {
-                                            cursor_position: input_string.len() as u64,
-                                            input: input_string.clone(),
-                                        });
-                                    drop(model);
-                                    let completed_command =
-                                        execute_shell_command(&shell, &input_string, prefix, rx);
-                                    let mut model =
-                                        thread_model_lock.lock().map_err(|_| "lock error").unwrap();
-                                    model.command_history.push(completed_command.clone());
-                                    model.current_command =
-                                        CurrentView::Output(completed_command.output.clone());
-                                    model.command_history_index = model.command_history.len();
-                                    let _ = model.add_current_directory_to_history();
-                                    model.mode = Mode::Idle;
-                                });
-                                model.mode = Mode::Executing(true, 0, tx, handle);
+                                model.set_current_view_from_command(
+                                    input_string.len() as u64,
+                                    input_string.clone(),
+                                );
+                                spawn_command(
+                                    model_lock,
+                                    &mut model,
+                                    input_string,
+                                    move |input, rx, progress| {
+                                        execute_shell_command(
+                                            &shell, input, prefix, rx, &options, progress,
+                                        )
+                                    },
+                                );
                             }
                             CurrentView::Output(_) => {
                                 // do nothing
@@ -1501,14 +4249,44 @@ pub(crate) fn update(
                             crate::HistoryType::CommandHistory => {
                                 model.config.history_type = crate::HistoryType::DirectoryHistory;
                             }
-                            crate::HistoryType::DirectoryHistory => {
+                            crate::HistoryType::DirectoryHistory | crate::HistoryType::Jobs => {
                                 model.config.history_type = crate::HistoryType::CommandHistory;
                             }
                         }
                         model.mode = Mode::Idle;
                         Ok(())
                     }
-                    Command::ChoosePath => {
+                    Command::ShowJobs => {
+                        model.config.history_type = match model.config.history_type {
+                            crate::HistoryType::Jobs => crate::HistoryType::CommandHistory,
+                            _ => crate::HistoryType::Jobs,
+                        };
+                        model.mode = Mode::Idle;
+                        Ok(())
+                    }
+                    Command::Kill(hint) => {
+                        model.mode = Mode::Idle;
+                        let index = match base26_to_base10(&hint) {
+                            Ok(index) if (index as usize) < model.jobs.len() => index as usize,
+                            Ok(_) => {
+                                model.status = Some(Status::Error(format!("no job {}", hint)));
+                                return Ok(());
+                            }
+                            Err(e) => {
+                                model.status = Some(Status::Error(format!("invalid hint: {}", e)));
+                                return Ok(());
+                            }
+                        };
+                        // dropping the sender as well stops the rest of a sequence
+                        let job = model.jobs.remove(index);
+                        let _ = job.cancel.send(());
+                        model.status = Some(Status::Info(format!(
+                            "[{}] killed: {}",
+                            job.number, job.input
+                        )));
+                        Ok(())
+                    }
+                    Command::ChoosePath(reopen) => {
                         match model.current_command {
                             CurrentView::CommandWithoutOutput(_) => {}
                             _ => {
@@ -1517,7 +4295,11 @@ pub(crate) fn update(
                             }
                         }
 
-                        let current_dir = std::env::current_dir();
+                        let current_dir = match &model.last_picker_directory {
+                            // the directory may have been removed since, then start over
+                            Some(last_dir) if reopen && last_dir.is_dir() => Ok(last_dir.clone()),
+                            _ => std::env::current_dir(),
+                        };
                         if current_dir.is_err() {
                             return Ok(());
                         }
@@ -1533,11 +4315,57 @@ pub(crate) fn update(
                             path: None,
                             current_dir,
                             children,
-                            location: None,
+                            ..Default::default()
                         });
-                        std::io::stdout().execute(crossterm::event::EnableMouseCapture)?;
+                        // without the mouse the picker is still operated with the keyboard
+                        if std::io::stdout()
+                            .execute(crossterm::event::EnableMouseCapture)
+                            .is_err()
+                        {
+                            model.status = Some(Status::Info(
+                                "no mouse support, use the arrow keys and enter".to_string(),
+                            ));
+                        }
                         Ok(())
                     }
+                    Command::Profile(amount) => {
+                        model.mode = Mode::Idle;
+                        let mut timed_commands = model
+                            .command_history
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(index, command)| {
+                                command
+                                    .duration
+                                    .map(|duration| (index, duration, &command.input))
+                            })
+                            .collect::<Vec<_>>();
+                        if timed_commands.is_empty() {
+                            return Ok(());
+                        }
+                        timed_commands.sort_by_key(|(_, duration, _)| std::cmp::Reverse(*duration));
+
+                        let mut table = format!("{:>6}  {:>10}  command\n", "number", "duration");
+                        for (index, duration, input) in timed_commands.into_iter().take(amount) {
+                            // the number shown in the history pane
+                            let number = model.pinned_commands.len() + model.command_history.len()
+                                - index
+                                - 1;
+                            table.push_str(&format!(
+                                "{:>6}  {:>9.2}s  {}\n",
+                                number,
+                                duration.as_secs_f64(),
+                                input
+                            ));
+                        }
+                        model.current_command = CurrentView::Output(Output {
+                            origin: Origin::Vshell,
+                            output_type: OutputType::Success(table, String::new(), None),
+                        });
+                        Ok(())
+                    }
+                    // SAFETY: repeats are replaced by the last edit before we get here
+                    Command::Repeat => unreachable!(),
                 }
             }
             _ => {
@@ -1545,6 +4373,134 @@ pub(crate) fn update(
                 Ok(())
             }
         },
+        Mode::Confirm(_) => {
+            let confirmed = matches!(event, event::Event::Character('y' | 'Y'));
+            if !confirmed
+                && !matches!(
+                    event,
+                    event::Event::Character(_) | event::Event::Enter | event::Event::Esc
+                )
+            {
+                return Ok(());
+            }
+            let Mode::Confirm(confirmation) = mem::replace(&mut model.mode, Mode::Idle) else {
+                unreachable!()
+            };
+            if !confirmed {
+                model.status = Some(Status::Info("cancelled".to_string()));
+                return Ok(());
+            }
+            match confirmation.action {
+                ConfirmedAction::Execute(input) => spawn_input(model_lock, &mut model, input),
+                ConfirmedAction::ExecuteAll(inputs) => {
+                    spawn_commands(model_lock, &mut model, inputs)
+                }
+                ConfirmedAction::ClearHistory(pins) => model.clear_history(pins),
+                ConfirmedAction::DeleteHistoryEntry(index) => {
+                    if index < model.command_history.len() {
+                        model.delete_history_entry(index);
+                    }
+                }
+            }
+            Ok(())
+        }
+        Mode::Search(_) => match event {
+            event::Event::Enter => {
+                let Mode::Search(search) = &model.mode else {
+                    unreachable!()
+                };
+                // SAFETY: the search only starts with a match
+                let matched = model.search_history(&search.query, search.matched).unwrap();
+                model.show_history_entry(matched);
+                if let Mode::Search(search) = &mut model.mode {
+                    search.matched = matched;
+                }
+                Ok(())
+            }
+            event::Event::Esc => {
+                model.mode = Mode::Idle;
+                Ok(())
+            }
+            event => {
+                // the shown command stays, the key is handled as if the search had ended
+                model.mode = Mode::Idle;
+                drop(model);
+                update(model_lock, event, clipboard)
+            }
+        },
+        Mode::Find(_) => match event {
+            event::Event::Enter => {
+                let Mode::Find(search) = &model.mode else {
+                    unreachable!()
+                };
+                // SAFETY: the search only starts with a match
+                let matched = model
+                    .find_in_output(&search.query, search.matched + 1)
+                    .unwrap();
+                if let Mode::Find(search) = &mut model.mode {
+                    search.matched = matched;
+                }
+                Ok(())
+            }
+            event::Event::Esc => {
+                model.mode = Mode::Idle;
+                Ok(())
+            }
+            event => {
+                model.mode = Mode::Idle;
+                drop(model);
+                update(model_lock, event, clipboard)
+            }
+        },
+        Mode::CopyWord(_) => {
+            // SAFETY: the mode is only entered while there is output
+            let output_string = model.output_string().unwrap();
+            let word_count = split_string(&output_string)
+                .iter()
+                .filter(|element| matches!(element, StringType::Word(_)))
+                .count() as u32;
+            let Mode::CopyWord(hint) = &mut model.mode else {
+                unreachable!()
+            };
+            match event {
+                event::Event::Character(c) if c.is_ascii_lowercase() => {
+                    hint.push(c);
+                    match base26_to_base10(hint) {
+                        Ok(index) if index < word_count => {
+                            if hint_is_complete(hint, word_count) {
+                                model.mode = Mode::Idle;
+                                clipboard.set_text(copy_words(&output_string, index, index));
+                            }
+                        }
+                        // no word has this hint
+                        _ => {
+                            hint.pop();
+                        }
+                    }
+                    Ok(())
+                }
+                event::Event::Backspace => {
+                    hint.pop();
+                    Ok(())
+                }
+                event::Event::Enter => {
+                    let index = base26_to_base10(hint).ok().filter(|_| !hint.is_empty());
+                    model.mode = Mode::Idle;
+                    if let Some(index) = index {
+                        clipboard.set_text(copy_words(&output_string, index, index));
+                    }
+                    Ok(())
+                }
+                event::Event::Esc => {
+                    model.mode = Mode::Idle;
+                    Ok(())
+                }
+                _ => {
+                    // do nothing
+                    Ok(())
+                }
+            }
+        }
         Mode::Directory(directory) => {
             fn position_in_list(list_location: Rect, position: (u16, u16)) -> Option<u16> {
                 let (x, y) = position;
@@ -1557,27 +4513,77 @@ pub(crate) fn update(
                 }
             }
 
+            fn select(directory: &mut Directory, selected: Option<usize>, preview: bool) {
+                directory.selected = selected;
+                directory.preview = match selected.map(|index| &directory.children[index]) {
+                    Some(File::File(file)) if preview => {
+                        Some(read_preview(&directory.current_dir.join(file)))
+                    }
+                    _ => None,
+                };
+            }
+
             fn set_children(directory: &mut Directory) -> std::io::Result<()> {
                 let children = get_directory_children(&directory.current_dir);
                 if children.is_none() {
                     return Ok(());
                 }
                 let children = children.unwrap();
+                select(directory, None, false);
                 if directory.search.is_empty() {
                     directory.children = children;
                 } else {
                     directory.children = children
                         .into_iter()
-                        .filter(|f| {
-                            f.to_string()
-                                .to_lowercase()
-                                .starts_with(&directory.search.to_lowercase())
-                        })
+                        .filter(|f| smart_case_starts_with(&directory.search, &f.to_string()))
                         .collect();
                 }
                 Ok(())
             }
 
+            /// Closes the picker and inserts `path` at the cursor.
+            fn close_with_path(model: &mut Model, path: &Path) {
+                if let Mode::Directory(directory) = mem::replace(&mut model.mode, Mode::Idle) {
+                    model.last_picker_directory = Some(directory.current_dir);
+                }
+                // mouse capture may never have been enabled
+                let _ = std::io::stdout().execute(crossterm::event::DisableMouseCapture);
+
+                let text_to_insert = path.to_string_lossy();
+                if let CurrentView::CommandWithoutOutput(command) = &mut model.current_command {
+                    command
+                        .input
+                        .insert_str(command.cursor_position as usize, &text_to_insert);
+                    command.cursor_position += text_to_insert.len() as u64;
+                }
+            }
+
+            /// Opens the directory or picks the file at `index` in the children.
+            fn open_child(model: &mut Model, index: usize) {
+                let Mode::Directory(directory) = &mut model.mode else {
+                    unreachable!()
+                };
+                directory.search = String::new();
+                match &directory.children[index] {
+                    File::Directory(directory_name) => {
+                        directory.current_dir.push(directory_name);
+                        let _ = set_children(directory);
+                    }
+                    File::File(file) => {
+                        let path = directory.current_dir.join(file);
+                        close_with_path(model, &path);
+                    }
+                }
+            }
+
+            fn open_parent(directory: &mut Directory) {
+                if let Some(parent) = directory.current_dir.parent() {
+                    directory.current_dir = parent.into();
+                    directory.search = String::new();
+                    let _ = set_children(directory);
+                }
+            }
+
             match event {
                 event::Event::MouseDown(x, y) => {
                     // SAFETY: if we reach this code the directory location has already been set by the view
@@ -1587,113 +4593,50 @@ pub(crate) fn update(
                         return Ok(());
                     }
                     let position = position.unwrap();
-                    directory.search = String::new();
                     if position == 1 {
-                        let parent = directory.current_dir.parent();
-                        if parent.is_none() {
-                            return Ok(());
-                        }
-                        let parent = parent.unwrap();
-                        directory.current_dir = parent.into();
+                        open_parent(directory);
                     } else if position == 0 {
-                        let path = directory.current_dir.to_string_lossy().to_string();
-                        model.mode = Mode::Idle;
-                        std::io::stdout().execute(crossterm::event::DisableMouseCapture)?;
-
-                        match &model.current_command {
-                            CurrentView::CommandWithoutOutput(command) => {
-                                let text_to_insert = path;
-                                if command.cursor_position == command.input.len() as u64 {
-                                    let new_command =
-                                        format!("{}{}", command.input, text_to_insert);
-                                    model.current_command =
-                                        CurrentView::CommandWithoutOutput(CommandWithoutOutput {
-                                            input: new_command,
-                                            cursor_position: command.cursor_position
-                                                + text_to_insert.len() as u64,
-                                        });
-
-                                    return Ok(());
-                                } else {
-                                    let (first, second) =
-                                        command.input.split_at(command.cursor_position as usize);
-                                    let new_command =
-                                        format!("{}{}{}", first, text_to_insert, second);
-                                    model.current_command =
-                                        CurrentView::CommandWithoutOutput(CommandWithoutOutput {
-                                            input: new_command,
-                                            cursor_position: command.cursor_position
-                                                + text_to_insert.len() as u64,
-                                        });
-                                    return Ok(());
-                                }
-                            }
-                            _ => unreachable!(),
-                        }
-                    } else {
-                        if position as i64 - 2 < 0
-                            || directory.children.len() <= position as usize - 2
-                        {
-                            return Ok(());
-                        }
-                        match &directory.children[position as usize - 2] {
-                            File::Directory(directory_name) => {
-                                directory.current_dir.push(directory_name);
-                            }
-                            File::File(file) => {
-                                directory.current_dir.push(file);
-                                let path = directory.current_dir.to_string_lossy().to_string();
-                                model.mode = Mode::Idle;
-                                std::io::stdout().execute(crossterm::event::DisableMouseCapture)?;
-
-                                match &model.current_command {
-                                    CurrentView::CommandWithoutOutput(command) => {
-                                        let text_to_insert = path;
-                                        if command.cursor_position == command.input.len() as u64 {
-                                            let new_command =
-                                                format!("{}{}", command.input, text_to_insert);
-                                            model.current_command =
-                                                CurrentView::CommandWithoutOutput(
-                                                    CommandWithoutOutput {
-                                                        input: new_command,
-                                                        cursor_position: command.cursor_position
-                                                            + text_to_insert.len() as u64,
-                                                    },
-                                                );
-
-                                            return Ok(());
-                                        } else {
-                                            let (first, second) = command
-                                                .input
-                                                .split_at(command.cursor_position as usize);
-                                            let new_command =
-                                                format!("{}{}{}", first, text_to_insert, second);
-                                            model.current_command =
-                                                CurrentView::CommandWithoutOutput(
-                                                    CommandWithoutOutput {
-                                                        input: new_command,
-                                                        cursor_position: command.cursor_position
-                                                            + text_to_insert.len() as u64,
-                                                    },
-                                                );
-                                            return Ok(());
-                                        }
-                                    }
-                                    _ => unreachable!(),
-                                }
-                            }
-                        }
+                        let path = directory.current_dir.clone();
+                        close_with_path(&mut model, &path);
+                    } else if (position as usize - 2) < directory.children.len() {
+                        open_child(&mut model, position as usize - 2);
                     }
-                    let children = get_directory_children(&directory.current_dir);
-                    if children.is_none() {
-                        return Ok(());
+                    Ok(())
+                }
+                event::Event::Up => {
+                    let selected = match directory.selected {
+                        Some(0) | None => directory.children.len().checked_sub(1),
+                        Some(index) => Some(index - 1),
+                    };
+                    select(directory, selected, picker_preview);
+                    Ok(())
+                }
+                event::Event::Down => {
+                    let selected = match directory.selected {
+                        Some(index) if index + 1 < directory.children.len() => Some(index + 1),
+                        _ if directory.children.is_empty() => None,
+                        _ => Some(0),
+                    };
+                    select(directory, selected, picker_preview);
+                    Ok(())
+                }
+                event::Event::Right => {
+                    if let Some(index) = directory.selected {
+                        if matches!(directory.children[index], File::Directory(_)) {
+                            open_child(&mut model, index);
+                        }
                     }
-                    directory.children = children.unwrap();
+                    Ok(())
+                }
+                event::Event::Left => {
+                    open_parent(directory);
                     Ok(())
                 }
                 event::Event::Esc => {
+                    let last_picker_directory = directory.current_dir.clone();
                     model.mode = Mode::Idle;
-                    std::io::stdout().execute(crossterm::event::DisableMouseCapture)?;
+                    model.last_picker_directory = Some(last_picker_directory);
+                    let _ = std::io::stdout().execute(crossterm::event::DisableMouseCapture);
                     Ok(())
                 }
                 event::Event::Character(c) => {
@@ -1701,21 +4644,30 @@ pub(crate) fn update(
                     let _ = set_children(directory);
                     Ok(())
                 }
-                event::Event::Backspace => {
-                    directory.search.pop();
+                event::Event::Backspace => {
+                    directory.search.pop();
+                    let _ = set_children(directory);
+                    Ok(())
+                }
+                event::Event::CtrlU => {
+                    directory.search.clear();
                     let _ = set_children(directory);
                     Ok(())
                 }
                 event::Event::Enter => {
                     let directory_path = PathBuf::from(directory.search.as_str());
-                    if directory_path.is_dir() {
+                    if let Some(index) = directory.selected {
+                        open_child(&mut model, index);
+                    } else if directory.search.is_empty() {
+                        // like clicking `.`
+                        let path = directory.current_dir.clone();
+                        close_with_path(&mut model, &path);
+                    } else if directory_path.is_dir() {
                         directory.current_dir = directory_path;
                         directory.search = String::new();
                         let _ = set_children(directory);
-                        Ok(())
-                    } else {
-                        Ok(())
                     }
+                    Ok(())
                 }
                 _ => {
                     // do nothing
@@ -1723,20 +4675,6 @@ pub(crate) fn update(
                 }
             }
         }
-        Mode::Executing(_, _, _, _) => {
-            if event == event::Event::CtrlC {
-                let executing_mode = mem::replace(&mut model.mode, Mode::Idle);
-                drop(model);
-                match executing_mode {
-                    Mode::Executing(_, _, sender, handle) => {
-                        sender.send(()).unwrap();
-                        handle.join().map_err(|_| "thread join error")?;
-                    }
-                    _ => unreachable!(),
-                }
-            }
-            Ok(())
-        }
     }
 }
 
@@ -1745,6 +4683,1159 @@ mod test {
     use super::*;
     #[test]
     fn test_base26_to_base10() {
-        assert_eq!(base26_to_base10("a"), Ok(0))
+        assert_eq!(base26_to_base10("a"), Ok(0));
+        assert_eq!(base26_to_base10("z"), Ok(25));
+        assert_eq!(base26_to_base10("aa"), Ok(26));
+        assert_eq!(base26_to_base10("ab"), Ok(27));
+        assert_eq!(base26_to_base10("az"), Ok(51));
+        assert_eq!(base26_to_base10("ba"), Ok(52));
+        assert_eq!(base26_to_base10("bb"), Ok(53));
+        assert_eq!(base26_to_base10("aaa"), Ok(702));
+        assert!(base26_to_base10("").is_err());
+        assert!(base26_to_base10("A").is_err());
+        assert!(base26_to_base10(&"z".repeat(10)).is_err());
+    }
+
+    #[test]
+    fn test_cd_command_quotes_path() {
+        let directory = PathBuf::from("/tmp/it's a \"quoted\" dir");
+        assert_eq!(
+            shlex::split(&cd_command(&directory)),
+            Some(vec![
+                "cd".to_string(),
+                "/tmp/it's a \"quoted\" dir".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_hint_range() {
+        let split_input = split_string("ls -l; echo  done");
+        assert_eq!(hint_range(&split_input, 0, 0), Some(0..=0));
+        assert_eq!(hint_range(&split_input, 1, 3), Some(2..=6));
+        assert_eq!(hint_range(&split_input, 2, 4), None);
+        assert_eq!(hint_range(&split_input, 2, 1), None);
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let text = "USER   PID  COMMAND\nroot   1    init\nshort\n";
+        assert_eq!(select_columns(text, 2, 2), "PID\n1\n\n");
+        assert_eq!(select_columns(text, 1, 2), "USER   PID\nroot   1\nshort\n");
+        assert_eq!(select_columns(text, 4, 5), "\n\n\n");
+    }
+
+    #[test]
+    fn test_align_pairs() {
+        assert_eq!(
+            align_pairs("Name: vshell\nVersion:0.1.0\n\nno delimiter\n"),
+            "Name    : vshell\nVersion : 0.1.0\n\nno delimiter\n"
+        );
+        // `=` comes first in more lines, the `:` in the values is left alone
+        assert_eq!(
+            align_pairs("HOME=/root\nPATH=/usr/bin:/bin\nnote: x\n"),
+            "HOME = /root\nPATH = /usr/bin:/bin\nnote: x\n"
+        );
+        assert_eq!(align_pairs("key:\n"), "key :\n");
+        assert_eq!(align_pairs(""), "");
+    }
+
+    #[test]
+    fn test_pasted_commands() {
+        assert_eq!(
+            pasted_commands("ls -la\n\n  cd /tmp  \r\necho 'unclosed\n"),
+            vec!["ls -la".to_string(), "cd /tmp".to_string()]
+        );
+        assert!(pasted_commands("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_copy_history() {
+        assert!(matches!(
+            Command::try_from("ch"),
+            Ok(Command::CopyHistory(false))
+        ));
+        assert!(matches!(
+            Command::try_from("copyhistory:outputs"),
+            Ok(Command::CopyHistory(true))
+        ));
+        assert!(Command::try_from("ch:all").is_err());
+
+        let history =
+            [("ls", "a\nb\n"), ("pwd", "/tmp\n")].map(|(input, stdout)| CompletedCommand {
+                input: input.to_string(),
+                output: Output {
+                    output_type: OutputType::Success(stdout.to_string(), String::new(), Some(0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        assert_eq!(history_text(&history, false), "ls\npwd");
+        assert_eq!(history_text(&history, true), "$ ls\na\nb\n\n$ pwd\n/tmp\n");
+        assert_eq!(history_text(&[], false), "");
+    }
+
+    #[test]
+    fn test_is_catastrophic_rm() {
+        let aliases = BTreeMap::from([("nuke".to_string(), "rm -rf /".to_string())]);
+        for input in [
+            "rm -rf /",
+            "rm -fr ~",
+            "sudo rm -r -f /*",
+            "rm --recursive --force $HOME/",
+            "/bin/rm -Rf -- ~/",
+            "true; rm -rf /",
+            "cd /tmp && rm -rf ~",
+            "echo | rm -rf /",
+            "rm -rf / &",
+            "nuke",
+        ] {
+            assert!(is_catastrophic_rm(input, &aliases), "{}", input);
+        }
+        for input in [
+            "rm -rf ./build",
+            "rm -r /",
+            "rm -f ~",
+            "echo rm -rf /",
+            "rm -rf ~/projects",
+            "echo 'rm -rf /; ls'",
+        ] {
+            assert!(!is_catastrophic_rm(input, &aliases), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let args = vec!["origin".to_string(), "feature-x".to_string()];
+        assert_eq!(
+            fill_template("git push {} {}", &args),
+            Ok("git push origin feature-x".to_string())
+        );
+        assert!(fill_template("git checkout {}", &args).is_err());
+        assert_eq!(fill_template("ls", &[]), Ok("ls".to_string()));
+    }
+
+    #[test]
+    fn test_grep_lines() {
+        let text = "Compiling a\nwarning: unused\nCompiling b\n";
+        assert_eq!(
+            grep_lines(text, "Compiling", false),
+            "Compiling a\nCompiling b\n"
+        );
+        assert_eq!(grep_lines(text, "Compiling", true), "warning: unused\n");
+        assert_eq!(grep_lines("no newline", "new", false), "no newline");
+    }
+
+    #[test]
+    fn test_copy_words() {
+        let output = "  first second\tthird\nfourth";
+        assert_eq!(copy_words(output, 0, 0), "first");
+        assert_eq!(copy_words(output, 1, 1), "second");
+        assert_eq!(copy_words(output, 1, 3), "second\tthird\nfourth");
+    }
+
+    #[test]
+    fn test_hint_is_complete() {
+        assert!(hint_is_complete("a", 26));
+        assert!(!hint_is_complete("a", 27));
+        assert!(hint_is_complete("b", 27));
+        assert!(hint_is_complete("b", 52));
+        assert!(!hint_is_complete("b", 53));
+        assert!(hint_is_complete("aa", 53));
+    }
+
+    #[test]
+    fn test_read_preview() {
+        let directory = std::env::temp_dir().join("vshell_test_read_preview");
+        std::fs::create_dir_all(&directory).unwrap();
+        let text = directory.join("text");
+        std::fs::write(&text, "hello\nworld\n").unwrap();
+        let binary = directory.join("binary");
+        std::fs::write(&binary, [0x7f, b'E', b'L', b'F', 0, 1]).unwrap();
+        // a multi-byte character cut in half at the end of the preview
+        let cut = directory.join("cut");
+        let mut content = "a".repeat(PREVIEW_BYTES as usize - 1);
+        content.push('ä');
+        std::fs::write(&cut, content).unwrap();
+
+        assert_eq!(
+            read_preview(&text),
+            Preview::Text("hello\nworld\n".to_string())
+        );
+        assert_eq!(read_preview(&binary), Preview::Binary);
+        assert_eq!(
+            read_preview(&cut),
+            Preview::Text("a".repeat(PREVIEW_BYTES as usize - 1))
+        );
+        assert!(matches!(
+            read_preview(&directory.join("missing")),
+            Preview::Unreadable(_)
+        ));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_picker_without_mouse() {
+        let directory = std::env::temp_dir().join("vshell_test_picker_without_mouse");
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+        std::fs::write(directory.join("sub").join("file"), "").unwrap();
+        std::fs::write(directory.join("other"), "").unwrap();
+
+        let model_lock = Arc::new(Mutex::new(Model::default()));
+        {
+            let mut model = model_lock.lock().unwrap();
+            model.set_current_view_from_command(4, "cat ".to_string());
+            model.mode = Mode::Directory(Directory {
+                current_dir: directory.clone(),
+                ..Default::default()
+            });
+        }
+        let press = |event| update(&model_lock, event, &mut Clipboard::new()).unwrap();
+        // narrow down to `sub`, open it and pick its only file
+        for event in [
+            event::Event::Character('s'),
+            event::Event::Down,
+            event::Event::Right,
+            event::Event::Down,
+            event::Event::Enter,
+        ] {
+            press(event);
+        }
+        let model = model_lock.lock().unwrap();
+        assert!(matches!(model.mode, Mode::Idle));
+        assert_eq!(
+            model.last_picker_directory.as_deref(),
+            Some(directory.join("sub").as_path())
+        );
+        match &model.current_command {
+            CurrentView::CommandWithoutOutput(command) => assert_eq!(
+                command.input,
+                format!("cat {}", directory.join("sub").join("file").display())
+            ),
+            _ => panic!("expected the picked path in the input"),
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_parse_watch() {
+        match Command::try_from("watch:2,ls -l a,b:c") {
+            Ok(Command::Watch(interval, command)) => {
+                assert_eq!(interval, Duration::from_secs(2));
+                assert_eq!(command, "ls -l a,b:c");
+            }
+            _ => panic!("expected a watch command"),
+        }
+        match Command::try_from("w:0.5,date") {
+            Ok(Command::Watch(interval, _)) => assert_eq!(interval, Duration::from_millis(500)),
+            _ => panic!("expected a watch command"),
+        }
+        assert!(Command::try_from("watch:0,ls").is_err());
+        assert!(Command::try_from("watch:ls").is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_colons() {
+        match Command::try_from("grep:12:30") {
+            Ok(Command::Grep(pattern, false)) => assert_eq!(pattern, "12:30"),
+            _ => panic!("expected a grep command"),
+        }
+        match Command::try_from("rg:a:b,c:d") {
+            Ok(Command::Replace(Replace::Global(from, to))) => {
+                assert_eq!((from.as_str(), to.as_str()), ("a:b", "c:d"))
+            }
+            _ => panic!("expected a replace command"),
+        }
+        match Command::try_from("se:bash,echo a:b; ") {
+            Ok(Command::ShellExecute(Some(shell), Some(prefix))) => {
+                assert_eq!((shell.as_str(), prefix.as_str()), ("bash", "echo a:b; "))
+            }
+            _ => panic!("expected a shell execute command"),
+        }
+        match Command::try_from("alias:now,date +%H:%M") {
+            Ok(Command::Alias(name, expansion)) => {
+                assert_eq!((name.as_str(), expansion.as_str()), ("now", "date +%H:%M"))
+            }
+            _ => panic!("expected an alias command"),
+        }
+        match Command::try_from("td:ssh,ssh {}:22") {
+            Ok(Command::DefineTemplate(name, template)) => {
+                assert_eq!((name.as_str(), template.as_str()), ("ssh", "ssh {}:22"))
+            }
+            _ => panic!("expected a template definition"),
+        }
+        match Command::try_from("qa:echo a:b") {
+            Ok(Command::QuitAfter(input)) => assert_eq!(input, "echo a:b"),
+            _ => panic!("expected a quit after command"),
+        }
+        // arguments that are hints or numbers still can't contain a colon
+        assert!(Command::try_from("c:a:b").is_err());
+        assert!(Command::try_from("co:a:b").is_err());
+        assert!(Command::try_from("gc:1:2").is_err());
+        assert!(Command::try_from("replay:1:2").is_err());
+    }
+
+    #[test]
+    fn test_parse_kill() {
+        match Command::try_from("kill:b") {
+            Ok(Command::Kill(hint)) => assert_eq!(hint, "b"),
+            _ => panic!("expected a kill command"),
+        }
+        assert!(Command::try_from("kill:").is_err());
+        assert!(Command::try_from("kill:A").is_err());
+        assert!(Command::try_from("kill:ä").is_err());
+        assert!(matches!(
+            Command::try_from("killjob:ab"),
+            Ok(Command::Kill(hint)) if hint == "ab"
+        ));
+        assert!(matches!(Command::try_from("jobs"), Ok(Command::ShowJobs)));
+        assert!(matches!(
+            Command::try_from("showjobs"),
+            Ok(Command::ShowJobs)
+        ));
+    }
+
+    /// Runs `input` as if it was typed after `:` and confirmed with enter.
+    fn enter_command(model_lock: &Arc<Mutex<Model>>, input: &str) {
+        model_lock.lock().unwrap().mode = Mode::Command(input.to_string());
+        update(model_lock, event::Event::Enter, &mut Clipboard::new()).unwrap();
+    }
+
+    fn current_input(model_lock: &Arc<Mutex<Model>>) -> String {
+        match &model_lock.lock().unwrap().current_command {
+            CurrentView::CommandWithoutOutput(command) => command.input.clone(),
+            _ => panic!("expected an input without output"),
+        }
+    }
+
+    #[test]
+    fn test_repeat_edit() {
+        assert!(matches!(Command::try_from("."), Ok(Command::Repeat)));
+        assert!(matches!(Command::try_from("repeat"), Ok(Command::Repeat)));
+
+        let model_lock = Arc::new(Mutex::new(Model::default()));
+        model_lock
+            .lock()
+            .unwrap()
+            .set_current_view_from_command(0, "a a a".to_string());
+        // nothing to repeat yet
+        enter_command(&model_lock, ".");
+        assert_eq!(current_input(&model_lock), "a a a");
+        assert!(matches!(model_lock.lock().unwrap().mode, Mode::Idle));
+
+        enter_command(&model_lock, "rs:a,b");
+        assert_eq!(current_input(&model_lock), "b a a");
+        enter_command(&model_lock, ".");
+        enter_command(&model_lock, "repeat");
+        assert_eq!(current_input(&model_lock), "b b b");
+        assert_eq!(
+            model_lock.lock().unwrap().last_edit.as_deref(),
+            Some("rs:a,b")
+        );
+    }
+
+    #[test]
+    fn test_profile() {
+        assert!(matches!(
+            Command::try_from("profile"),
+            Ok(Command::Profile(10))
+        ));
+        assert!(matches!(
+            Command::try_from("profile:3"),
+            Ok(Command::Profile(3))
+        ));
+        assert!(Command::try_from("profile:x").is_err());
+
+        let model_lock = Arc::new(Mutex::new(Model::default()));
+        for (input, duration) in [("make", Some(2)), ("ls", None), ("cargo test", Some(5))] {
+            model_lock
+                .lock()
+                .unwrap()
+                .command_history
+                .push(CompletedCommand {
+                    input: input.to_string(),
+                    duration: duration.map(Duration::from_secs),
+                    ..Default::default()
+                });
+        }
+        enter_command(&model_lock, "profile:1");
+        let model = model_lock.lock().unwrap();
+        match &model.current_command {
+            CurrentView::Output(Output {
+                output_type: OutputType::Success(table, _, _),
+                ..
+            }) => {
+                let rows = table.lines().collect::<Vec<&str>>();
+                assert_eq!(rows.len(), 2);
+                // the slowest command with its number in the history pane
+                assert!(rows[1].trim_start().starts_with("0 "));
+                assert!(rows[1].ends_with("5.00s  cargo test"));
+            }
+            _ => panic!("expected the profile table"),
+        }
+    }
+
+    #[test]
+    fn test_toggle_hidden() {
+        assert!(matches!(
+            Command::try_from("ct:1"),
+            Ok(Command::ToggleHidden(1))
+        ));
+        assert!(matches!(
+            Command::try_from("commenttoggle:2"),
+            Ok(Command::ToggleHidden(2))
+        ));
+        assert!(Command::try_from("ct").is_err());
+        assert!(Command::try_from("ct:a").is_err());
+        assert!(matches!(
+            Command::try_from("showhidden"),
+            Ok(Command::ShowHidden)
+        ));
+
+        let model_lock = Arc::new(Mutex::new(Model::default()));
+        for input in ["ls", "date"] {
+            model_lock
+                .lock()
+                .unwrap()
+                .command_history
+                .push(CompletedCommand {
+                    input: input.to_string(),
+                    ..Default::default()
+                });
+        }
+        // numbered from the newest entry
+        enter_command(&model_lock, "ct:1");
+        let hidden = |model_lock: &Arc<Mutex<Model>>| {
+            model_lock
+                .lock()
+                .unwrap()
+                .command_history
+                .iter()
+                .map(|command| command.hidden)
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(hidden(&model_lock), [true, false]);
+        enter_command(&model_lock, "ct:1");
+        assert_eq!(hidden(&model_lock), [false, false]);
+        // numbers past the history are ignored
+        enter_command(&model_lock, "ct:5");
+        assert_eq!(hidden(&model_lock), [false, false]);
+
+        enter_command(&model_lock, "shd");
+        assert!(model_lock.lock().unwrap().config.show_hidden);
+        enter_command(&model_lock, "shd");
+        assert!(!model_lock.lock().unwrap().config.show_hidden);
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert!(matches!(Command::try_from("tk"), Ok(Command::Tokens)));
+        assert!(matches!(Command::try_from("tokens"), Ok(Command::Tokens)));
+
+        let model_lock = Arc::new(Mutex::new(Model::default()));
+        let tokens = |input: &str| {
+            model_lock
+                .lock()
+                .unwrap()
+                .set_current_view_from_command(0, input.to_string());
+            enter_command(&model_lock, "tokens");
+            let model = model_lock.lock().unwrap();
+            match &model.current_command {
+                CurrentView::CommandWithOutput(command) => {
+                    assert_eq!(command.input, input);
+                    command.output.output_type.clone()
+                }
+                _ => panic!("expected the tokens as output"),
+            }
+        };
+        assert_eq!(
+            tokens(r#"echo "a b" c\ d"#),
+            OutputType::Success(
+                "\"echo\"\n\"a b\"\n\"c d\"\n".to_string(),
+                String::new(),
+                None
+            )
+        );
+        assert!(matches!(
+            tokens("echo 'a b"),
+            OutputType::Error(_, error, None) if error.contains("unbalanced")
+        ));
+    }
+
+    /// Waits until the commands started by the last `enter_command` completed.
+    fn wait_for_commands(model_lock: &Arc<Mutex<Model>>) {
+        let mode = mem::take(&mut model_lock.lock().unwrap().mode);
+        if let Mode::Executing(Execution { handle, cancel, .. }) = mode {
+            handle.join().unwrap();
+            // dropping the sender would cancel the commands
+            drop(cancel);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replay() {
+        assert!(matches!(
+            Command::try_from("replay:2"),
+            Ok(Command::Replay(2))
+        ));
+        assert!(Command::try_from("replay").is_err());
+        assert!(Command::try_from("replay:0").is_err());
+        assert!(Command::try_from("replay:x").is_err());
+
+        let model_lock = Arc::new(Mutex::new(Model {
+            directory_history: vec![std::env::current_dir().unwrap()],
+            ..Default::default()
+        }));
+        for input in ["echo a", "echo b", "echo c"] {
+            model_lock
+                .lock()
+                .unwrap()
+                .command_history
+                .push(CompletedCommand {
+                    input: input.to_string(),
+                    ..Default::default()
+                });
+        }
+        enter_command(&model_lock, "replay:2");
+        wait_for_commands(&model_lock);
+        let model = model_lock.lock().unwrap();
+        let inputs = model
+            .command_history
+            .iter()
+            .map(|command| command.input.as_str())
+            .collect::<Vec<&str>>();
+        // the last two commands run again in their original order
+        assert_eq!(inputs, ["echo a", "echo b", "echo c", "echo b", "echo c"]);
+        assert!(matches!(
+            &model.command_history[4].output.output_type,
+            OutputType::Success(stdout, _, Some(0)) if stdout == "c\n"
+        ));
+    }
+
+    #[test]
+    fn test_sleep() {
+        assert!(matches!(
+            Command::try_from("sleep:0.5"),
+            Ok(Command::Sleep(duration)) if duration == Duration::from_millis(500)
+        ));
+        assert!(Command::try_from("sleep").is_err());
+        assert!(Command::try_from("sleep:-1").is_err());
+        assert!(Command::try_from("sleep:x").is_err());
+
+        let model_lock = Arc::new(Mutex::new(Model {
+            directory_history: vec![std::env::current_dir().unwrap()],
+            ..Default::default()
+        }));
+        enter_command(&model_lock, "sleep:0");
+        wait_for_commands(&model_lock);
+        // replaying the history entry sleeps in vshell instead of running `sleep`
+        enter_command(&model_lock, "replay:1");
+        wait_for_commands(&model_lock);
+        let model = model_lock.lock().unwrap();
+        assert_eq!(model.command_history.len(), 2);
+        for command in &model.command_history {
+            assert_eq!(command.input, ":sleep:0");
+            assert_eq!(command.output.origin, Origin::Vshell);
+            assert!(matches!(
+                command.output.output_type,
+                OutputType::Success(_, _, Some(0))
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_and_pin() {
+        assert!(matches!(Command::try_from("rp"), Ok(Command::RunAndPin)));
+        assert!(matches!(
+            Command::try_from("runandpin"),
+            Ok(Command::RunAndPin)
+        ));
+
+        let model_lock = Arc::new(Mutex::new(Model {
+            directory_history: vec![std::env::current_dir().unwrap()],
+            ..Default::default()
+        }));
+        model_lock
+            .lock()
+            .unwrap()
+            .command_history
+            .push(CompletedCommand {
+                input: "echo a".to_string(),
+                ..Default::default()
+            });
+        model_lock
+            .lock()
+            .unwrap()
+            .set_current_view_from_command(2, "!!".to_string());
+        enter_command(&model_lock, "rp");
+        wait_for_commands(&model_lock);
+        let model = model_lock.lock().unwrap();
+        // pinned as typed, run expanded
+        let pinned = model
+            .pinned_commands
+            .iter()
+            .map(|command| command.input.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(pinned, ["!!"]);
+        assert_eq!(model.command_history.len(), 2);
+        assert_eq!(model.command_history[1].input, "echo a");
+    }
+
+    #[test]
+    fn test_parse_pipe() {
+        match Command::try_from("pipe: wc -l") {
+            Ok(Command::Pipe(input)) => assert_eq!(input, "wc -l"),
+            _ => panic!("expected a pipe command"),
+        }
+        assert!(Command::try_from("pipe").is_err());
+        assert!(Command::try_from("pipe: ").is_err());
+    }
+
+    #[test]
+    fn test_parse_buffer() {
+        match Command::try_from("buf:save a") {
+            Ok(Command::Buffer(Buffer::Save(name))) => assert_eq!(name, "a"),
+            _ => panic!("expected a buffer command"),
+        }
+        match Command::try_from("buffer:show,before") {
+            Ok(Command::Buffer(Buffer::Show(name))) => assert_eq!(name, "before"),
+            _ => panic!("expected a buffer command"),
+        }
+        assert!(Command::try_from("buf:save").is_err());
+        assert!(Command::try_from("buf:drop a").is_err());
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = BTreeMap::from([
+            ("ll".to_string(), "ls -la".to_string()),
+            ("ls".to_string(), "ls --color".to_string()),
+            ("loop".to_string(), "again".to_string()),
+            ("again".to_string(), "loop x".to_string()),
+        ]);
+        assert_eq!(expand_aliases("ll src", &aliases), "ls --color -la src");
+        assert_eq!(
+            expand_aliases("cat ll | ll&& echo 'a;ll'", &aliases),
+            "cat ll | ls --color -la&& echo 'a;ll'"
+        );
+        assert_eq!(expand_aliases("loop", &aliases), "loop x");
+        assert_eq!(expand_aliases("  ls;pwd", &aliases), "  ls --color;pwd");
+        assert_eq!(expand_aliases("lsd", &aliases), "lsd");
+    }
+
+    #[test]
+    fn test_parse_everywhere() {
+        match Command::try_from("everywhere: git status") {
+            Ok(Command::Everywhere(input)) => assert_eq!(input, "git status"),
+            _ => panic!("expected an everywhere command"),
+        }
+        assert!(matches!(
+            Command::try_from("ev:ls"),
+            Ok(Command::Everywhere(_))
+        ));
+        assert!(matches!(
+            Command::try_from("execinalldirs:ls"),
+            Ok(Command::Everywhere(_))
+        ));
+        assert!(Command::try_from("everywhere").is_err());
+        assert!(Command::try_from("everywhere: ").is_err());
+    }
+
+    #[test]
+    fn test_unique_directories() {
+        let missing = PathBuf::from("/vshell/no/such/directory");
+        let history = [
+            PathBuf::from("/tmp"),
+            missing,
+            PathBuf::from("/"),
+            PathBuf::from("/tmp"),
+        ];
+        assert_eq!(
+            unique_directories(&history),
+            [PathBuf::from("/tmp"), PathBuf::from("/")]
+        );
+    }
+
+    #[test]
+    fn test_parse_select_hint() {
+        match Command::try_from("sel:c") {
+            Ok(Command::Select(Some(number))) => assert_eq!(number, 2),
+            _ => panic!("expected a select command"),
+        }
+        match Command::try_from("sel:ab") {
+            Ok(Command::Select(Some(number))) => assert_eq!(number, 27),
+            _ => panic!("expected a select command"),
+        }
+        assert!(Command::try_from("sel").is_err());
+        assert!(Command::try_from("sel:1").is_err());
+    }
+
+    #[test]
+    fn test_parse_clip() {
+        match Command::try_from("clip:3") {
+            Ok(Command::CopyHistoryOutput(3, CopyOutput::All)) => {}
+            _ => panic!("expected a clip command"),
+        }
+        match Command::try_from("clipo:12:b,d") {
+            Ok(Command::CopyHistoryOutput(12, CopyOutput::Range(beginning, end))) => {
+                assert_eq!((beginning.as_str(), end.as_str()), ("b", "d"))
+            }
+            _ => panic!("expected a clip command"),
+        }
+        match Command::try_from("clip:0:c") {
+            Ok(Command::CopyHistoryOutput(0, CopyOutput::Single(hint))) => assert_eq!(hint, "c"),
+            _ => panic!("expected a clip command"),
+        }
+        assert!(Command::try_from("clip").is_err());
+        assert!(Command::try_from("clip:x").is_err());
+        assert!(Command::try_from("clip:1:").is_err());
+        assert!(Command::try_from("clip:1:A").is_err());
+        assert!(Command::try_from("clip:1:a,É").is_err());
+        assert!(Command::try_from("c:A").is_err());
+        assert!(matches!(
+            Command::try_from("c:a,c"),
+            Ok(Command::Edit(Edit::Range(..)))
+        ));
+    }
+
+    #[test]
+    fn test_copied_text() {
+        let output = "first second third";
+        assert_eq!(
+            copied_text(output, CopyOutput::All).unwrap().as_deref(),
+            Some(output)
+        );
+        assert_eq!(
+            copied_text(output, CopyOutput::Single("b".to_string()))
+                .unwrap()
+                .as_deref(),
+            Some("second")
+        );
+        assert_eq!(
+            copied_text(output, CopyOutput::Range("c".to_string(), "a".to_string())).unwrap(),
+            None
+        );
+        assert!(copied_text(output, CopyOutput::Single("A".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_parse_select_command_number() {
+        match Command::try_from("s:#42") {
+            Ok(Command::SelectCommandNumber(number)) => assert_eq!(number, 42),
+            _ => panic!("expected a select command"),
+        }
+        match Command::try_from("s:3") {
+            Ok(Command::Select(Some(number))) => assert_eq!(number, 3),
+            _ => panic!("expected a select command"),
+        }
+        assert!(Command::try_from("s:#").is_err());
+        assert!(Command::try_from("s:#x").is_err());
+    }
+
+    #[test]
+    fn test_parse_find() {
+        match Command::try_from("f:Error") {
+            Ok(Command::FindInOutput(query)) => assert_eq!(query, "Error"),
+            _ => panic!("expected a find command"),
+        }
+        match Command::try_from("find:exit code") {
+            Ok(Command::FindInOutput(query)) => assert_eq!(query, "exit code"),
+            _ => panic!("expected a find command"),
+        }
+        assert!(Command::try_from("f").is_err());
+        assert!(Command::try_from("f:").is_err());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        match Command::try_from("ver: git ") {
+            Ok(Command::Version(program)) => assert_eq!(program, "git"),
+            _ => panic!("expected a version command"),
+        }
+        assert!(matches!(
+            Command::try_from("whichversion:git"),
+            Ok(Command::Version(_))
+        ));
+        assert!(Command::try_from("ver").is_err());
+        assert!(Command::try_from("ver: ").is_err());
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        match Command::try_from("alias:ll,ls -la") {
+            Ok(Command::Alias(name, expansion)) => {
+                assert_eq!((name.as_str(), expansion.as_str()), ("ll", "ls -la"))
+            }
+            _ => panic!("expected an alias command"),
+        }
+        assert!(matches!(
+            Command::try_from("alias"),
+            Ok(Command::ShowAliases)
+        ));
+        assert!(Command::try_from("alias:l l,ls").is_err());
+    }
+
+    #[test]
+    fn test_find_executable() {
+        let vshell = std::env::current_exe().unwrap();
+        assert_eq!(find_executable(&vshell.to_string_lossy()), Some(vshell));
+        assert_eq!(find_executable("vshell-no-such-program"), None);
+        assert_eq!(find_executable("/vshell/no/such/program"), None);
+    }
+
+    #[test]
+    fn test_word_jump() {
+        let input = "git  commit\t-m 'x'";
+        assert_eq!(word_jump(input, input.len(), false), 15);
+        assert_eq!(word_jump(input, 15, false), 12);
+        assert_eq!(word_jump(input, 8, false), 5);
+        assert_eq!(word_jump(input, 5, false), 0);
+        assert_eq!(word_jump(input, 0, false), 0);
+        assert_eq!(word_jump(input, 0, true), 3);
+        assert_eq!(word_jump(input, 3, true), 11);
+        assert_eq!(word_jump(input, 12, true), 14);
+        assert_eq!(word_jump(input, input.len(), true), input.len());
+    }
+
+    #[test]
+    fn test_kill_line() {
+        assert_eq!(
+            kill_line("git commit -m", 4, true),
+            ("git ".to_string(), "commit -m".to_string())
+        );
+        assert_eq!(
+            kill_line("git commit -m", 4, false),
+            ("commit -m".to_string(), "git ".to_string())
+        );
+        assert_eq!(kill_line("ls", 2, true), ("ls".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_delete_word_backward() {
+        assert_eq!(
+            delete_word_backward("git commit -m", 13),
+            ("git commit ".to_string(), 11)
+        );
+        // the whitespace in front of the cursor goes with the word
+        assert_eq!(
+            delete_word_backward("git commit   ", 13),
+            ("git ".to_string(), 4)
+        );
+        assert_eq!(
+            delete_word_backward("ls src/main.rs -la", 14),
+            ("ls  -la".to_string(), 3)
+        );
+        assert_eq!(delete_word_backward("ls", 0), ("ls".to_string(), 0));
+        assert_eq!(delete_word_backward("  ", 2), ("".to_string(), 0));
+    }
+
+    #[test]
+    fn test_completed_word() {
+        assert_eq!(completed_word("gi", 2), (0, true));
+        assert_eq!(completed_word("git sta", 7), (4, false));
+        assert_eq!(completed_word("ls src | gr", 11), (9, true));
+        assert_eq!(completed_word("cat ", 4), (4, false));
+        assert_eq!(completed_word("ls sr tail", 5), (3, false));
+    }
+
+    #[test]
+    fn test_completion_candidates() {
+        let directory = std::env::temp_dir().join("vshell_test_completion_candidates");
+        std::fs::create_dir_all(directory.join("src")).unwrap();
+        for file in ["setup.sh", "settings.toml", ".secret"] {
+            std::fs::write(directory.join(file), "").unwrap();
+        }
+        let d = directory.to_string_lossy();
+
+        assert_eq!(
+            completion_candidates(&format!("{}/se", d), false),
+            [format!("{d}/settings.toml"), format!("{d}/setup.sh")]
+        );
+        assert_eq!(completion_candidates(&format!("{}/s", d), true).len(), 3);
+        assert_eq!(
+            completion_candidates(&format!("{}/sr", d), false),
+            [format!("{d}/src/")]
+        );
+        assert_eq!(
+            completion_candidates(&format!("{}/.s", d), false),
+            [format!("{d}/.secret")]
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_quote_completion() {
+        assert_eq!(quote_completion("src/main.rs"), "src/main.rs");
+        assert_eq!(quote_completion("my file"), "\"my file\"");
+        assert_eq!(quote_completion("~/my file"), "~/\"my file\"");
+        assert_eq!(quote_completion("~/src/"), "~/src/");
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let candidates = ["settings.toml".to_string(), "setup.sh".to_string()];
+        assert_eq!(common_prefix(&candidates), "set");
+        assert_eq!(common_prefix(&candidates[..1]), "settings.toml");
+        assert_eq!(common_prefix(&["ab".to_string(), "abc".to_string()]), "ab");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn test_char_position() {
+        assert_eq!(char_position("ls -la", 3), 3);
+        assert_eq!(char_position("ls -la", 42), 6);
+        assert_eq!(char_position("echo äö", 6), 7);
+        assert_eq!(char_position("", 0), 0);
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        std::env::set_var("VSHELL_TEST_VARIABLE", "a b'c");
+        assert_eq!(
+            shlex::split(&expand_variables(
+                "echo $VSHELL_TEST_VARIABLE x${VSHELL_TEST_VARIABLE}y \"$VSHELL_TEST_VARIABLE\""
+            )),
+            Some(vec![
+                "echo".to_string(),
+                "a b'c".to_string(),
+                "xa b'cy".to_string(),
+                "a b'c".to_string(),
+            ])
+        );
+        assert_eq!(
+            expand_variables("echo '$VSHELL_TEST_VARIABLE' \\$VSHELL_TEST_VARIABLE $ ${"),
+            "echo '$VSHELL_TEST_VARIABLE' \\$VSHELL_TEST_VARIABLE $ ${"
+        );
+        assert_eq!(expand_variables("echo $VSHELL_UNSET_VARIABLE."), "echo .");
+    }
+
+    #[test]
+    fn test_expand_globs() {
+        let directory = std::env::temp_dir().join("vshell_test_expand_globs");
+        std::fs::create_dir_all(&directory).unwrap();
+        for file in ["a.rs", "b.rs", "c.txt", ".hidden.rs"] {
+            std::fs::write(directory.join(file), "").unwrap();
+        }
+        let d = directory.to_string_lossy();
+
+        assert_eq!(
+            expand_globs(&format!("ls {}/*.rs|cat", d), None),
+            format!("ls {d}/a.rs {d}/b.rs|cat")
+        );
+        assert_eq!(
+            expand_globs(&format!("ls \"{}\"/[bc]*", d), None),
+            format!("ls {d}/b.rs {d}/c.txt")
+        );
+        let quoted = format!("ls '{d}/*.rs' {d}/\\*.rs {d}/*.nothing");
+        assert_eq!(expand_globs(&quoted, None), quoted);
+        assert_eq!(
+            expand_globs("ls *.rs ../*.nothing", Some(&directory)),
+            "ls a.rs b.rs ../*.nothing"
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_background_command() {
+        assert_eq!(background_command("sleep 60 &"), Some("sleep 60"));
+        assert_eq!(background_command("sleep 60& "), Some("sleep 60"));
+        assert_eq!(background_command("sleep 60"), None);
+        assert_eq!(background_command("ls &&"), None);
+        assert_eq!(background_command(" &"), None);
+        assert_eq!(background_command("echo '&'"), None);
+        assert_eq!(background_command("echo \\&"), None);
+    }
+
+    #[test]
+    fn test_split_sequence() {
+        assert_eq!(
+            split_sequence("mkdir build && cd build || echo 'a;b'; ls;"),
+            Ok(vec![
+                (Condition::Always, "mkdir build "),
+                (Condition::Succeeded, " cd build "),
+                (Condition::Failed, " echo 'a;b'"),
+                (Condition::Always, " ls"),
+            ])
+        );
+        assert_eq!(
+            split_sequence("ls | wc"),
+            Ok(vec![(Condition::Always, "ls | wc")])
+        );
+        assert!(split_sequence("&& ls").is_err());
+        assert!(split_sequence("ls ;; ls").is_err());
+        assert!(split_sequence("ls &&").is_err());
+    }
+
+    #[test]
+    fn test_run_sequence() {
+        let run = |input| {
+            let mut ran = Vec::new();
+            let succeeded = run_sequence(
+                &split_sequence(input).unwrap(),
+                |command| {
+                    ran.push(command.trim().to_string());
+                    command.trim() == "true"
+                },
+                || false,
+            );
+            (succeeded, ran)
+        };
+        assert_eq!(
+            run("false; true"),
+            (true, vec!["false".into(), "true".into()])
+        );
+        assert_eq!(
+            run("false || true"),
+            (true, vec!["false".into(), "true".into()])
+        );
+        assert_eq!(
+            run("true; false"),
+            (false, vec!["true".into(), "false".into()])
+        );
+        assert_eq!(run("false && true"), (false, vec!["false".into()]));
+        assert_eq!(run("true || false"), (true, vec!["true".into()]));
+        assert_eq!(
+            run("false && x; true"),
+            (true, vec!["false".into(), "true".into()])
+        );
+        // nothing runs after a cancelled command
+        let mut ran = 0;
+        let succeeded = run_sequence(
+            &split_sequence("false; true").unwrap(),
+            |_| {
+                ran += 1;
+                false
+            },
+            || true,
+        );
+        assert_eq!((succeeded, ran), (false, 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sequence_status() {
+        let run = |input: &str| {
+            let model_lock = Arc::new(Mutex::new(Model {
+                directory_history: vec![std::env::current_dir().unwrap()],
+                ..Default::default()
+            }));
+            model_lock
+                .lock()
+                .unwrap()
+                .set_current_view_from_command(input.len() as u64, input.to_string());
+            update(&model_lock, event::Event::Enter, &mut Clipboard::new()).unwrap();
+            wait_for_commands(&model_lock);
+            let model = model_lock.lock().unwrap();
+            model.command_history[0].output.output_type.clone()
+        };
+        // the status and exit code are the ones of the last command that ran
+        assert!(matches!(
+            run("false; true"),
+            OutputType::Success(_, _, Some(0))
+        ));
+        assert!(matches!(
+            run("false || true"),
+            OutputType::Success(_, _, Some(0))
+        ));
+        assert!(matches!(
+            run("true; false"),
+            OutputType::Error(_, _, Some(1))
+        ));
+        assert!(matches!(
+            run("false && true"),
+            OutputType::Error(_, _, Some(1))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_streamed_output() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "printf out; printf err >&2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut streamed_output = StreamedOutput::new(&mut child);
+        let status = child.wait();
+        let (progress, progress_rx) = std::sync::mpsc::channel::<Progress>();
+        // the output is read on other threads, wait until all of it arrived
+        let start = Instant::now();
+        while streamed_output.reported < 6 && start.elapsed() < Duration::from_secs(5) {
+            streamed_output.report(&Origin::Vshell, &progress);
+        }
+        streamed_output.report(&Origin::Vshell, &progress);
+        assert!(matches!(
+            progress_rx.try_iter().last(),
+            Some(Progress::Output(Output {
+                output_type: OutputType::Success(out, err, None),
+                ..
+            })) if out == "out" && err == "err"
+        ));
+
+        let output = streamed_output.finish(status).unwrap();
+        assert_eq!(output.stdout, b"out");
+        assert_eq!(output.stderr, b"err");
+    }
+
+    #[test]
+    fn test_split_pipeline() {
+        assert_eq!(
+            split_pipeline("ls -la | grep 'a|b' |wc -l"),
+            Ok(vec![
+                vec!["ls".to_string(), "-la".to_string()],
+                vec!["grep".to_string(), "a|b".to_string()],
+                vec!["wc".to_string(), "-l".to_string()],
+            ])
+        );
+        assert_eq!(
+            split_pipeline("echo \\| \"|\" a||b"),
+            Ok(vec![vec![
+                "echo".to_string(),
+                "|".to_string(),
+                "|".to_string(),
+                "a||b".to_string(),
+            ]])
+        );
+        assert!(split_pipeline("ls |").is_err());
+        assert!(split_pipeline("| ls").is_err());
+    }
+
+    #[test]
+    fn test_expand_history() {
+        let mut model = Model::default();
+        for input in ["ls", "apt update"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                output: Output::default(),
+                ..Default::default()
+            });
+        }
+        model.pinned_commands.push(CommandWithoutOutput {
+            cursor_position: 0,
+            input: "cargo test".to_string(),
+        });
+
+        assert_eq!(expand_history("!!", &model), Ok("apt update".to_string()));
+        assert_eq!(
+            expand_history("sudo !!", &model),
+            Ok("sudo apt update".to_string())
+        );
+        assert_eq!(expand_history("!0", &model), Ok("cargo test".to_string()));
+        assert_eq!(expand_history("!2 -la", &model), Ok("ls -la".to_string()));
+        assert_eq!(
+            expand_history("echo !! && !0", &model),
+            Ok("echo apt update && cargo test".to_string())
+        );
+        assert_eq!(
+            expand_history("echo '!!' \\!! !", &model),
+            Ok("echo '!!' \\!! !".to_string())
+        );
+        assert_eq!(
+            expand_history("!3", &model),
+            Err("!3: event not found".to_string())
+        );
     }
 }
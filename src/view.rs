@@ -1,13 +1,77 @@
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
+    layout::{Alignment, Constraint, Layout, Rect},
     style::{Style, Stylize},
-    text::Line,
-    widgets::{Block, Borders, Clear, ListItem, Paragraph, Widget, Wrap},
+    text::{Line, Span},
+    widgets::{
+        block::Title, Block, Borders, Cell, Clear, ListItem, Paragraph, Row, Table, Widget, Wrap,
+    },
 };
 
-use crate::{split_string, CurrentView, File, Mode, Model, OutputType, StringType};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    ansi, format_relative_time, smart_case_matches, split_string, CurrentView, DirectoryDisplay,
+    File, Mode, Model, OutputType, PaneSize, Phase, Preview, Progress, Status, StringType, Theme,
+};
+
+#[derive(Debug, PartialEq)]
+enum Pane {
+    Input,
+    Output,
+}
+
+/// The pane keys currently go to, popups like the command line take the focus from all panes.
+fn focused_pane(model: &Model) -> Option<Pane> {
+    match model.mode {
+        Mode::Idle => Some(Pane::Input),
+        Mode::Executing(_) if model.config.async_execution => Some(Pane::Input),
+        Mode::Executing(_) | Mode::CopyWord(_) | Mode::Find(_) => Some(Pane::Output),
+        Mode::Search(_) => Some(Pane::Input),
+        Mode::Command(_) | Mode::Confirm(_) | Mode::Directory(_) | Mode::Quit => None,
+    }
+}
+
+fn border_style(focused: bool, theme: Theme) -> Style {
+    match focused {
+        true => Style::default().fg(theme.accent),
+        false => Style::default().dark_gray(),
+    }
+}
+
+/// `text` with its first `hint_length` bytes, the hint in front of a word, in the hint color.
+fn hinted_line(text: &str, hint_length: usize, theme: Theme) -> Line<'_> {
+    let (hint, rest) = text.split_at(floor_char_boundary(text, hint_length));
+    Line::from(vec![
+        Span::styled(hint, Style::default().fg(theme.hint)),
+        Span::raw(rest),
+    ])
+}
+
+// below this the panes are too small for their borders and content
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
 
 pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
+    let theme = model.config.theme;
+    let size = frame.size();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        safe_render(
+            frame,
+            Paragraph::new("terminal too small")
+                .fg(theme.foreground)
+                .bg(theme.background)
+                .wrap(Wrap { trim: true }),
+            size,
+            size.height,
+        );
+        return;
+    }
+    let focus = focused_pane(model);
     let outer_layout = ratatui::layout::Layout::default()
         .direction(ratatui::layout::Direction::Horizontal)
         .constraints(vec![
@@ -27,9 +91,10 @@ pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
     safe_render(
         frame,
         ratatui::widgets::Block::new()
-            .white()
-            .on_black()
-            .borders(ratatui::widgets::Borders::ALL),
+            .fg(theme.foreground)
+            .bg(theme.background)
+            .borders(ratatui::widgets::Borders::ALL)
+            .border_style(border_style(focus == Some(Pane::Input), model.config.theme)),
         left_layout[0],
         frame.size().height,
     );
@@ -37,9 +102,10 @@ pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
     safe_render(
         frame,
         ratatui::widgets::Block::new()
-            .white()
-            .on_black()
-            .borders(ratatui::widgets::Borders::ALL),
+            .fg(theme.foreground)
+            .bg(theme.background)
+            .borders(ratatui::widgets::Borders::ALL)
+            .border_style(border_style(false, model.config.theme)),
         left_layout[1],
         frame.size().height,
     );
@@ -53,9 +119,35 @@ pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
         crate::HistoryType::DirectoryHistory => {
             render_directory_history(frame, model, left_layout[1]);
         }
+        crate::HistoryType::Jobs => {
+            render_jobs(frame, model, left_layout[1]);
+        }
+    }
+
+    let match_count = match &model.mode {
+        Mode::Search(search) => Some((
+            model.history_match_count(&search.query, search.matched),
+            left_layout[1],
+        )),
+        Mode::Find(search) => Some((
+            model.output_match_count(&search.query, search.matched),
+            outer_layout[1],
+        )),
+        _ => None,
+    };
+    if let Some(((position, total), pane)) = match_count {
+        render_match_count(frame, position, total, pane, theme);
     }
 
-    if let Mode::Command(command) = &model.mode {
+    let prompt = match &model.mode {
+        Mode::Command(command) => Some((command.as_str(), Style::default().fg(theme.foreground))),
+        Mode::Confirm(confirmation) => Some((
+            confirmation.message.as_str(),
+            Style::default().fg(theme.error),
+        )),
+        _ => None,
+    };
+    if let Some((prompt, style)) = prompt {
         safe_render(
             frame,
             Clear,
@@ -70,8 +162,14 @@ pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
 
         safe_render(
             frame,
-            ratatui::widgets::Paragraph::new(command.as_str())
-                .block(Block::new().white().on_black().bold().borders(Borders::ALL))
+            ratatui::widgets::Paragraph::new(prompt)
+                .block(
+                    Block::new()
+                        .style(style)
+                        .bg(theme.background)
+                        .bold()
+                        .borders(Borders::ALL),
+                )
                 .wrap(Wrap { trim: false }),
             Rect {
                 x: outer_layout[0].x,
@@ -84,18 +182,114 @@ pub(crate) fn view(model: &mut Model, frame: &mut ratatui::Frame) {
     }
 
     render_directory_view(model, frame);
+    render_mode_indicator(frame, model);
+    render_status(frame, model);
+}
+
+fn render_status(frame: &mut ratatui::Frame, model: &Model) {
+    let theme = model.config.theme;
+    let (message, style) = match &model.status {
+        Some(Status::Info(message)) => (
+            message,
+            Style::default().fg(theme.foreground).bg(theme.background),
+        ),
+        Some(Status::Error(message)) => (
+            message,
+            Style::default().fg(theme.error).bg(theme.background),
+        ),
+        None => return,
+    };
+
+    let size = frame.size();
+    // leave room for the mode indicator on the right
+    let width = display_width(message).min(size.width.saturating_sub(16));
+    if width == 0 || size.height == 0 {
+        return;
+    }
+
+    safe_render(
+        frame,
+        Paragraph::new(message.as_str()).style(style.bold()),
+        Rect {
+            x: 1,
+            y: size.height - 1,
+            width,
+            height: 1,
+        },
+        size.height,
+    );
+}
+
+fn render_mode_indicator(frame: &mut ratatui::Frame, model: &Model) {
+    let theme = &model.config.theme;
+    let (label, color) = match &model.mode {
+        Mode::Idle => (" NORMAL ".to_string(), theme.accent),
+        Mode::Command(_) => (" COMMAND ".to_string(), theme.accent),
+        Mode::Directory(_) => (" PICK ".to_string(), theme.directory),
+        Mode::Confirm(_) => (" CONFIRM ".to_string(), theme.error),
+        Mode::Search(search) => (format!(" SEARCH {} ", search.query), theme.accent),
+        Mode::Find(search) => (format!(" FIND {} ", search.query), theme.accent),
+        Mode::CopyWord(hint) if hint.is_empty() => (" COPY ".to_string(), theme.hint),
+        Mode::CopyWord(hint) => (format!(" COPY {} ", hint), theme.hint),
+        Mode::Executing(_) => (" RUN ".to_string(), theme.cursor),
+        Mode::Quit => return,
+    };
+
+    let size = frame.size();
+    if size.width < label.len() as u16 + 2 || size.height == 0 {
+        return;
+    }
+
+    safe_render(
+        frame,
+        Paragraph::new(label.as_str())
+            .style(Style::default().fg(theme.background).bg(color).bold()),
+        Rect {
+            x: size.width - label.len() as u16 - 1,
+            y: size.height - 1,
+            width: label.len() as u16,
+            height: 1,
+        },
+        size.height,
+    );
+}
+
+/// Shows which match of a search is selected at the right end of the top border of `pane`.
+fn render_match_count(
+    frame: &mut ratatui::Frame,
+    position: usize,
+    total: usize,
+    pane: Rect,
+    theme: Theme,
+) {
+    let label = format!(" {}/{} matches ", position, total);
+    let width = label.len() as u16;
+    if pane.width < width + 2 {
+        return;
+    }
+    safe_render(
+        frame,
+        Paragraph::new(label).style(Style::default().fg(theme.accent).bg(theme.background)),
+        Rect {
+            x: pane.x + pane.width - width - 1,
+            y: pane.y,
+            width,
+            height: 1,
+        },
+        frame.size().height,
+    );
 }
 
-fn base10_to_base26(mut num: u32) -> String {
+/// The hint of the word with the index `num`, in bijective base 26 like spreadsheet columns:
+/// `a` to `z`, then `aa` to `az`, `ba` and so on.
+fn base10_to_base26(num: u32) -> String {
     let mut result = String::new();
+    let mut num = num as u64 + 1;
     while num > 0 {
-        let digit = (num % 26) as u8;
-        result.push((digit + b'a') as char);
+        num -= 1;
+        result.push((b'a' + (num % 26) as u8) as char);
         num /= 26;
     }
-    if result.is_empty() {
-        result.push('a');
-    }
     result.chars().rev().collect() // Reverse to get the correct order
 }
 
@@ -109,7 +303,146 @@ where
         frame.render_widget(widget, area)
     }
 }
+/// Renders a part of the scrollable output. `area.y` counts the lines of the whole output
+/// starting at 1, lines outside of the visible part of the output pane are skipped.
+fn render_scrolled<W>(frame: &mut ratatui::Frame, widget: W, area: Rect, layout: Rect, offset: u16)
+where
+    W: Widget,
+{
+    if area.y <= offset {
+        return;
+    }
+    let y = layout.y + area.y - offset;
+    if y + 1 >= layout.y + layout.height {
+        return;
+    }
+    safe_render(frame, widget, Rect { y, ..area }, frame.size().height);
+}
+
+/// Returns the largest index that is at most `index` and does not cut through a character.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    (0..=index.min(s.len()))
+        .rev()
+        .find(|i| s.is_char_boundary(*i))
+        .unwrap_or(0)
+}
+
+/// The number of columns `s` takes up in the terminal. Zero-width and combining characters take
+/// none and wide characters take two.
+fn display_width(s: &str) -> u16 {
+    UnicodeWidthStr::width(s) as u16
+}
+
+/// Returns the largest index whose prefix of `s` fits into `columns` columns without cutting
+/// through a character.
+fn split_index_for_width(s: &str, columns: u16) -> usize {
+    let mut width = 0;
+    for (index, c) in s.char_indices() {
+        width += UnicodeWidthChar::width(c).unwrap_or(0);
+        if width > columns as usize {
+            return index;
+        }
+    }
+    s.len()
+}
+
+/// The number of rows `lines` take up when a paragraph of `width` columns wraps them at word
+/// boundaries without trimming, following the rules of ratatui's word wrapper.
+fn wrapped_line_count(lines: &[Line], width: u16) -> u16 {
+    if width == 0 {
+        return 0;
+    }
+    let mut count = 0;
+    for line in lines {
+        let mut rows = 0;
+        // the widths of what is in the current row, the word and the whitespace before it
+        let (mut row_width, mut row_is_empty) = (0, true);
+        let (mut word_width, mut word_is_empty) = (0, true);
+        let mut whitespaces = VecDeque::new();
+        let mut whitespace_width = 0;
+        let mut has_seen_non_whitespace = false;
+        // like graphemes, combining characters belong to the character before them
+        let mut symbols: Vec<(u16, bool)> = Vec::new();
+        let mut after_control = true;
+        for c in line.spans.iter().flat_map(|span| span.content.chars()) {
+            let c_width = UnicodeWidthChar::width(c).map(|width| width as u16);
+            match symbols.last_mut() {
+                Some((_, is_whitespace))
+                    if c_width == Some(0) && !c.is_whitespace() && !after_control =>
+                {
+                    *is_whitespace = false
+                }
+                _ => symbols.push((c_width.unwrap_or(0), c.is_whitespace() && c != '\u{a0}')),
+            }
+            after_control = c.is_control();
+        }
+        for (c_width, is_whitespace) in symbols {
+            if c_width > width {
+                continue;
+            }
+            if has_seen_non_whitespace && is_whitespace
+                || word_width + whitespace_width + c_width > width && row_is_empty
+            {
+                row_width += whitespace_width + word_width;
+                row_is_empty &= whitespaces.is_empty() && word_is_empty;
+                whitespaces.clear();
+                (whitespace_width, word_width, word_is_empty) = (0, 0, true);
+            }
+            if row_width >= width
+                || row_width + whitespace_width + word_width >= width && c_width > 0
+            {
+                let mut remaining_width = width.saturating_sub(row_width);
+                rows += 1;
+                (row_width, row_is_empty) = (0, true);
+                // the whitespace that still fits into the finished row is dropped with it
+                let mut first_whitespace = whitespaces.pop_front();
+                while let Some(whitespace) = first_whitespace {
+                    whitespace_width -= whitespace;
+                    if whitespace > remaining_width {
+                        break;
+                    }
+                    remaining_width -= whitespace;
+                    first_whitespace = whitespaces.pop_front();
+                }
+                if is_whitespace && first_whitespace.is_none() {
+                    continue;
+                }
+            }
+            if is_whitespace {
+                whitespace_width += c_width;
+                whitespaces.push_back(c_width);
+            } else {
+                word_width += c_width;
+                word_is_empty = false;
+            }
+            has_seen_non_whitespace = !is_whitespace;
+        }
+        if !row_is_empty || !word_is_empty || !whitespaces.is_empty() {
+            rows += 1;
+        }
+        count += rows.max(1);
+    }
+    count.min(u16::MAX as usize) as u16
+}
+
+/// The cursor cell at `x` in row `y` of the input pane. Right of the last column the cursor
+/// wraps to the start of the next row, or stays on the last column if this is the last row.
+fn input_cursor_location(x: u16, y: u16, layout: Rect) -> Rect {
+    let (x, y) = match x >= layout.width - 1 {
+        true if y + 2 < layout.height => (1, y + 1),
+        true => (layout.width - 2, y),
+        false => (x, y),
+    };
+    Rect {
+        x,
+        y,
+        width: 1,
+        height: 1,
+    }
+}
+
 fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
+    let theme = model.config.theme;
     let writable_width = layout.width - 2;
     let mut x = 1;
     let mut y = 1;
@@ -121,7 +454,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
     if string_that_was_split.is_empty() {
         safe_render(
             frame,
-            Block::new().on_green(),
+            Block::new().bg(theme.cursor),
             Rect {
                 x,
                 y,
@@ -153,7 +486,16 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                 };
 
                 let mut string_to_render = format!("{}{}", hint, content);
-                if x + 1 + string_to_render.len() as u16 > layout.width {
+                // a word that fits on a row of its own moves there instead of being split,
+                // only longer words are broken up
+                if x > 1
+                    && x + 1 + display_width(&string_to_render) > layout.width
+                    && display_width(&string_to_render) <= writable_width
+                {
+                    x = 1;
+                    y += 1;
+                }
+                if x + 1 + display_width(&string_to_render) > layout.width {
                     let mut character_amount = 0;
                     let mut space_left = layout.width - x - 1;
                     // safe_render(frame,
@@ -169,29 +511,45 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                             y += 1;
                             space_left = writable_width;
                         }
-                        let current_string = if string_to_render.len() as u16 <= space_left {
+                        let current_string = if display_width(&string_to_render) <= space_left {
                             should_quit = true;
                             string_to_render.clone()
                         } else {
-                            let mut c = string_to_render.split_off(space_left as usize);
+                            let mut split_index =
+                                split_index_for_width(&string_to_render, space_left);
+                            if split_index == 0 {
+                                if space_left < writable_width {
+                                    // not even the first character fits, continue on the next line
+                                    space_left = 0;
+                                    continue;
+                                }
+                                split_index =
+                                    string_to_render.chars().next().map_or(0, char::len_utf8);
+                            }
+                            let mut c = string_to_render.split_off(split_index);
                             std::mem::swap(&mut c, &mut string_to_render);
                             c
                         };
 
-                        space_left = layout.width - x - 1 - current_string.len() as u16;
+                        space_left =
+                            (layout.width - x - 1).saturating_sub(display_width(&current_string));
 
                         let location = Rect {
                             x,
                             y,
-                            width: current_string.len() as u16,
+                            width: display_width(&current_string),
                             height: 1,
                         };
 
                         safe_render(
                             frame,
-                            Paragraph::new(current_string.as_str())
-                                .block(Block::new().white().on_black())
-                                .wrap(Wrap { trim: false }),
+                            Paragraph::new(hinted_line(
+                                &current_string,
+                                hint.len().saturating_sub(character_amount as usize),
+                                theme,
+                            ))
+                            .block(Block::new().fg(theme.foreground).bg(theme.background))
+                            .wrap(Wrap { trim: false }),
                             location,
                             frame.size().height,
                         );
@@ -203,42 +561,41 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 && cursor_position_inside_content + hint.len() as u64
                                     <= character_amount + current_string.len() as u64
                             {
-                                let new_x =
-                                    x + cursor_position_inside_content as u16 + hint.len() as u16
-                                        - character_amount as u16;
-                                let cursor_location = Rect {
-                                    x: if new_x == layout.width - 1 { 1 } else { new_x },
-                                    y: if new_x == layout.width - 1 { y + 1 } else { y },
-                                    width: 1,
-                                    height: 1,
-                                };
+                                let before_cursor = floor_char_boundary(
+                                    &current_string,
+                                    (cursor_position_inside_content + hint.len() as u64
+                                        - character_amount)
+                                        as usize,
+                                );
+                                let new_x = x + display_width(&current_string[..before_cursor]);
+                                let cursor_location = input_cursor_location(new_x, y, layout);
                                 safe_render(
                                     frame,
-                                    Block::new().on_green(),
+                                    Block::new().bg(theme.cursor),
                                     cursor_location,
                                     frame.size().height,
                                 );
                             }
                         }
                         character_amount += current_string.len() as u64;
-                        x += current_string.len() as u16;
+                        x += display_width(&current_string);
                     }
                 } else {
                     let location = Rect {
                         x,
                         y,
-                        width: string_to_render.len() as u16,
+                        width: display_width(&string_to_render),
                         height: 1,
                     };
                     safe_render(
                         frame,
-                        Paragraph::new(string_to_render.as_str())
-                            .block(Block::new().white().on_black())
+                        Paragraph::new(hinted_line(&string_to_render, hint.len(), theme))
+                            .block(Block::new().fg(theme.foreground).bg(theme.background))
                             .wrap(Wrap { trim: false }),
                         location,
                         frame.size().height,
                     );
-                    x += string_to_render.len() as u16;
+                    x += display_width(&string_to_render);
 
                     if let Some(cursor_position_inside_content) = cursor_position_inside_content {
                         if !(cursor_position_inside_content == content.len() as u64
@@ -246,24 +603,18 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                             && string_that_was_split[word_index + 1] == StringType::Tab
                             && x + TAB_STRING.len() as u16 > layout.width - 1)
                         {
-                            let new_x = location.x
-                                + cursor_position_inside_content as u16
-                                + hint.len() as u16;
-                            let cursor_location = Rect {
-                                x: if new_x == layout.width - 1 { 1 } else { new_x },
-                                y: if new_x == layout.width - 1 {
-                                    location.y + 1
-                                } else {
-                                    location.y
-                                },
-                                width: 1,
-                                height: 1,
-                            };
+                            let before_cursor = floor_char_boundary(
+                                &string_to_render,
+                                hint.len() + cursor_position_inside_content as usize,
+                            );
+                            let new_x =
+                                location.x + display_width(&string_to_render[..before_cursor]);
+                            let cursor_location = input_cursor_location(new_x, location.y, layout);
 
                             if cursor_position_inside_content == content.len() as u64 {
                                 safe_render(
                                     frame,
-                                    Block::new().on_green(),
+                                    Block::new().bg(theme.cursor),
                                     cursor_location,
                                     frame.size().height,
                                 );
@@ -271,10 +622,16 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 safe_render(
                                     frame,
                                     Paragraph::new(
-                                        &content[cursor_position_inside_content as usize
-                                            ..=cursor_position_inside_content as usize],
+                                        content[floor_char_boundary(
+                                            content,
+                                            cursor_position_inside_content as usize,
+                                        )..]
+                                            .chars()
+                                            .next()
+                                            .map(String::from)
+                                            .unwrap_or_default(),
                                     )
-                                    .block(Block::new().white().on_green()),
+                                    .block(Block::new().fg(theme.foreground).bg(theme.cursor)),
                                     cursor_location,
                                     frame.size().height,
                                 );
@@ -303,11 +660,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                     let mut new_cursor_position = None;
 
                     if distance_from_x == 0 {
-                        if x > writable_width {
-                            new_cursor_position = Some((1, y + 1));
-                        } else {
-                            new_cursor_position = Some((x, y));
-                        }
+                        new_cursor_position = Some((x, y));
                     }
 
                     for _ in content.chars() {
@@ -322,31 +675,26 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                             x += 1;
                             distance_from_x = distance_from_x.saturating_sub(1);
                             if distance_from_x == 0 && new_cursor_position.is_none() {
-                                if x > writable_width {
-                                    new_cursor_position = Some((1, y + 1));
-                                } else {
-                                    new_cursor_position = Some((x, y));
-                                }
+                                new_cursor_position = Some((x, y));
                             }
                         }
                     }
 
+                    // a cursor in front of a word is drawn by the word, which may have moved to the
+                    // next row
+                    let next = string_that_was_split.get(word_index + 1);
                     if !(cursor_position_inside_content == content.len() as u64
-                        && string_that_was_split.get(word_index + 1).is_some()
-                        && string_that_was_split[word_index + 1] == StringType::Tab
-                        && x + TAB_STRING.len() as u16 > layout.width - 1)
+                        && (matches!(next, Some(StringType::Word(_)))
+                            || next == Some(&StringType::Tab)
+                                && x + TAB_STRING.len() as u16 > layout.width - 1))
                     {
-                        let cursor_location = Rect {
-                            // SAFETY: new_cursor_position is always Some if cursor_position_inside_content is Some
-                            x: new_cursor_position.unwrap().0,
-                            y: new_cursor_position.unwrap().1,
-                            width: 1,
-                            height: 1,
-                        };
+                        // SAFETY: new_cursor_position is always Some if cursor_position_inside_content is Some
+                        let (x, y) = new_cursor_position.unwrap();
+                        let cursor_location = input_cursor_location(x, y, layout);
 
                         safe_render(
                             frame,
-                            Block::new().on_green(),
+                            Block::new().bg(theme.cursor),
                             cursor_location,
                             frame.size().height,
                         );
@@ -387,7 +735,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                 safe_render(
                     frame,
                     Paragraph::new(TAB_STRING)
-                        .block(Block::new().white().on_black())
+                        .block(Block::new().fg(theme.foreground).bg(theme.background))
                         .wrap(Wrap { trim: false }),
                     location,
                     frame.size().height,
@@ -404,7 +752,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                             };
                             safe_render(
                                 frame,
-                                Block::new().on_green(),
+                                Block::new().bg(theme.cursor),
                                 cursor_location,
                                 frame.size().height,
                             );
@@ -430,7 +778,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 };
                                 safe_render(
                                     frame,
-                                    Block::new().on_green(),
+                                    Block::new().bg(theme.cursor),
                                     cursor_location,
                                     frame.size().height,
                                 );
@@ -463,7 +811,7 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
 
                     safe_render(
                         frame,
-                        Block::new().on_green(),
+                        Block::new().bg(theme.cursor),
                         cursor_location,
                         frame.size().height,
                     );
@@ -484,7 +832,12 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
         safe_render(
             frame,
             ratatui::widgets::Paragraph::new(header.as_str())
-                .block(Block::new().white().on_black().bold())
+                .block(
+                    Block::new()
+                        .fg(theme.foreground)
+                        .bg(theme.background)
+                        .bold(),
+                )
                 .wrap(Wrap { trim: false }),
             Rect {
                 x: 0,
@@ -498,7 +851,12 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
         safe_render(
             frame,
             ratatui::widgets::Paragraph::new(directory_header.as_str())
-                .block(Block::new().white().on_black().bold())
+                .block(
+                    Block::new()
+                        .fg(theme.foreground)
+                        .bg(theme.background)
+                        .bold(),
+                )
                 .wrap(Wrap { trim: false }),
             Rect {
                 x: 0,
@@ -511,45 +869,166 @@ fn render_input(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
     }
 }
 
+/// How a word of the output that matches the `:f` query is highlighted.
+fn found_style(current: bool, theme: Theme) -> Style {
+    match current {
+        true => Style::default().fg(theme.background).bg(theme.accent),
+        false => Style::default().reversed(),
+    }
+}
+
 fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
-    let (output, block, origin) = match &model.current_command {
-        CurrentView::CommandWithoutOutput(_) => {
-            (None, Block::new().white().on_black().bold(), None)
+    let theme = model.config.theme;
+    let found = match &model.mode {
+        Mode::Find(search) => Some((search.query.clone(), search.matched)),
+        _ => None,
+    };
+    if let Mode::Executing(execution) = &mut model.mode {
+        while let Ok(progress) = execution.progress.try_recv() {
+            match progress {
+                // the next command of a sequence starts, its output replaces the previous one
+                Progress::Phase(Phase::Parsing) => {
+                    execution.phase = Phase::Parsing;
+                    execution.output = None;
+                }
+                Progress::Phase(phase) => execution.phase = phase,
+                Progress::Output(output) => execution.output = Some(output),
+            }
+        }
+    }
+    let streamed_output = match &model.mode {
+        Mode::Executing(execution) if model.output_replaces_current_view() => {
+            execution.output.as_ref()
+        }
+        _ => None,
+    };
+    let completions = model
+        .completion
+        .as_ref()
+        .map(|completion| &completion.output);
+    let shown_output = streamed_output
+        .or(completions)
+        .or(match &model.current_command {
+            // with async execution the latest output stays visible while the next command is typed
+            CurrentView::CommandWithoutOutput(_) if model.config.async_execution => model
+                .command_history
+                .last()
+                .map(|completed_command| &completed_command.output),
+            CurrentView::CommandWithoutOutput(_) => None,
+            CurrentView::Output(o) => Some(o),
+            CurrentView::CommandWithOutput(o) => Some(&o.output),
+        });
+    // whether the shown output differs from the previous run of the same input
+    let changed = match &model.current_command {
+        _ if streamed_output.is_some() || completions.is_some() => None,
+        CurrentView::CommandWithoutOutput(_) if model.config.async_execution => model
+            .command_history
+            .last()
+            .and_then(|completed_command| completed_command.changed),
+        CurrentView::CommandWithoutOutput(_) => None,
+        CurrentView::Output(o) => model
+            .command_history
+            .last()
+            .filter(|completed_command| completed_command.output == *o)
+            .and_then(|completed_command| completed_command.changed),
+        CurrentView::CommandWithOutput(o) => o.changed,
+    };
+    let exit_code = match shown_output.map(|o| &o.output_type) {
+        Some(OutputType::Success(_, _, exit_code)) | Some(OutputType::Error(_, _, exit_code)) => {
+            *exit_code
         }
-        CurrentView::Output(o) => match o.output_type {
-            OutputType::Success(_, _) | OutputType::Empty => (
+        _ => None,
+    };
+    // with `split_output` stderr gets a pane of its own below stdout
+    let split_streams = match shown_output.map(|o| &o.output_type) {
+        Some(OutputType::Success(stdout, stderr, _))
+        | Some(OutputType::Error(stdout, stderr, _))
+            if model.config.split_output && !stdout.is_empty() && !stderr.is_empty() =>
+        {
+            Some((stdout.clone(), stderr.clone()))
+        }
+        _ => None,
+    };
+    let (output, block, origin) = match shown_output {
+        None => (
+            None,
+            Block::new()
+                .fg(theme.foreground)
+                .bg(theme.background)
+                .bold(),
+            None,
+        ),
+        Some(o) => match o.output_type {
+            OutputType::Success(_, _, _) | OutputType::Empty => (
                 Some(o.to_string()),
-                Block::new().white().on_black().bold(),
+                Block::new()
+                    .fg(theme.foreground)
+                    .bg(theme.background)
+                    .bold(),
                 Some(o.origin.clone()),
             ),
-            OutputType::Error(_, _) => (
+            OutputType::Error(_, _, _) => (
                 Some(o.to_string()),
-                Block::new().red().on_black().bold(),
+                Block::new().fg(theme.error).bg(theme.background).bold(),
                 Some(o.origin.clone()),
             ),
         },
-        CurrentView::CommandWithOutput(o) => match o.output.output_type {
-            OutputType::Success(_, _) | OutputType::Empty => (
-                Some(o.output.to_string()),
-                Block::new().white().on_black().bold(),
-                Some(o.output.origin.clone()),
-            ),
-            OutputType::Error(_, _) => (
-                Some(o.output.to_string()),
-                Block::new().red().on_black().bold(),
-                Some(o.output.origin.clone()),
-            ),
-        },
     };
 
+    let output_border = border_style(
+        focused_pane(model) == Some(Pane::Output),
+        model.config.theme,
+    );
+    model.output_scroll.visible_lines = layout.height.saturating_sub(2);
+    model.output_size = PaneSize {
+        columns: layout.width.saturating_sub(2),
+        lines: layout.height.saturating_sub(2),
+    };
+    // like `tail -f`, output that is still being written stays scrolled to the bottom
+    if matches!(model.mode, Mode::Executing(_)) && !model.output_scroll.detached {
+        model.output_scroll.offset = model.output_scroll.max_offset();
+    }
+    let mut scroll_offset = model.output_scroll.offset;
+    // the row of the word that was found, it is scrolled into view
+    let mut found_row = None;
+    safe_render(
+        frame,
+        Block::new().bg(theme.background),
+        layout,
+        frame.size().height,
+    );
     if let Some(output) = output {
         match model.config.hint_state {
+            _ if model.table_view => {
+                let rows = table_rows(&ansi::strip(&output));
+                let widths = column_widths(&rows)
+                    .into_iter()
+                    .map(Constraint::Length)
+                    .collect::<Vec<Constraint>>();
+                model.output_scroll.total_lines = rows.len() as u16;
+                let table = Table::new(
+                    rows.into_iter()
+                        .skip(scroll_offset as usize)
+                        .map(|row| Row::new(row.into_iter().map(Cell::from))),
+                    widths,
+                )
+                .column_spacing(2)
+                .block(
+                    block
+                        .clone()
+                        .borders(Borders::ALL)
+                        .border_style(output_border),
+                );
+                safe_render(frame, table, layout, frame.size().height);
+            }
             crate::HintState::ShowHints => {
                 let writable_width = layout.width - 2;
                 let mut x = layout.x + 1;
                 let mut y = 1;
                 let mut index = 0;
 
+                // hints have to line up with the words as they are shown, without escape codes
+                let output = ansi::strip(&output);
                 let string_that_was_split = split_string(&output);
 
                 for word in string_that_was_split.iter() {
@@ -562,8 +1041,15 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 crate::HintState::HideHints => String::new(),
                             };
 
+                            let found_style = found.as_ref().and_then(|(query, matched)| {
+                                smart_case_matches(query, content)
+                                    .then(|| found_style(index == *matched, theme))
+                            });
+                            if found.as_ref().is_some_and(|(_, matched)| index == *matched) {
+                                found_row = Some(y);
+                            }
                             let mut string_to_render = format!("{}{}", hint, content);
-                            if x + 1 + string_to_render.len() as u16 > layout.width + layout.x {
+                            if x + 1 + display_width(&string_to_render) > layout.width + layout.x {
                                 let mut space_left = layout.x + layout.width - x - 1;
                                 // safe_render(frame,
                                 //     Paragraph::new(space_left.to_string())
@@ -572,61 +1058,89 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 //     layout,
                                 // );
                                 let mut should_quit = false;
+                                // bytes of `hint` and `content` rendered so far
+                                let mut rendered = 0;
                                 while !should_quit {
                                     if space_left == 0 {
                                         x = layout.x + 1;
                                         y += 1;
                                         space_left = writable_width;
                                     }
-                                    let current_string = if string_to_render.len() as u16
+                                    let current_string = if display_width(&string_to_render)
                                         <= space_left
                                     {
                                         should_quit = true;
                                         string_to_render.clone()
                                     } else {
-                                        let mut c = string_to_render.split_off(space_left as usize);
+                                        let mut split_index =
+                                            split_index_for_width(&string_to_render, space_left);
+                                        if split_index == 0 {
+                                            if space_left < writable_width {
+                                                // not even the first character fits, continue on the next line
+                                                space_left = 0;
+                                                continue;
+                                            }
+                                            split_index = string_to_render
+                                                .chars()
+                                                .next()
+                                                .map_or(0, char::len_utf8);
+                                        }
+                                        let mut c = string_to_render.split_off(split_index);
                                         std::mem::swap(&mut c, &mut string_to_render);
                                         c
                                     };
 
-                                    space_left = layout.x + layout.width
-                                        - x
-                                        - 1
-                                        - current_string.len() as u16;
+                                    space_left = (layout.x + layout.width - x - 1)
+                                        .saturating_sub(display_width(&current_string));
 
                                     let location = Rect {
                                         x,
                                         y,
-                                        width: current_string.len() as u16,
+                                        width: display_width(&current_string),
                                         height: 1,
                                     };
 
-                                    safe_render(
+                                    let mut line = hinted_line(
+                                        &current_string,
+                                        hint.len().saturating_sub(rendered),
+                                        theme,
+                                    );
+                                    if let Some(style) = found_style {
+                                        line.patch_style(style);
+                                    }
+                                    render_scrolled(
                                         frame,
-                                        Paragraph::new(current_string.as_str())
-                                            .block(Block::new().white().on_black())
+                                        Paragraph::new(line)
+                                            .block(block.clone())
                                             .wrap(Wrap { trim: false }),
                                         location,
-                                        frame.size().height,
+                                        layout,
+                                        scroll_offset,
                                     );
-                                    x += current_string.len() as u16;
+                                    x += display_width(&current_string);
+                                    rendered += current_string.len();
                                 }
                             } else {
                                 let location = Rect {
                                     x,
                                     y,
-                                    width: string_to_render.len() as u16,
+                                    width: display_width(&string_to_render),
                                     height: 1,
                                 };
-                                safe_render(
+                                let mut line = hinted_line(&string_to_render, hint.len(), theme);
+                                if let Some(style) = found_style {
+                                    line.patch_style(style);
+                                }
+                                render_scrolled(
                                     frame,
-                                    Paragraph::new(string_to_render.as_str())
-                                        .block(Block::new().white().on_black())
+                                    Paragraph::new(line)
+                                        .block(block.clone())
                                         .wrap(Wrap { trim: false }),
                                     location,
-                                    frame.size().height,
+                                    layout,
+                                    scroll_offset,
                                 );
-                                x += string_to_render.len() as u16;
+                                x += display_width(&string_to_render);
                             }
 
                             index += 1;
@@ -652,13 +1166,14 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                                 width: TAB_STRING.len() as u16,
                                 height: 1,
                             };
-                            safe_render(
+                            render_scrolled(
                                 frame,
                                 Paragraph::new(TAB_STRING)
-                                    .block(Block::new().white().on_black())
+                                    .block(block.clone())
                                     .wrap(Wrap { trim: false }),
                                 location,
-                                frame.size().height,
+                                layout,
+                                scroll_offset,
                             );
                             x += TAB_STRING.len() as u16;
                         }
@@ -668,37 +1183,157 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                         }
                     }
                 }
+                model.output_scroll.total_lines = y;
+            }
+            crate::HintState::HideHints if found.is_some() => {
+                // SAFETY: checked by the guard
+                let (query, matched) = found.unwrap();
+                let output = ansi::strip(&output);
+                let mut lines = Vec::new();
+                let mut spans = Vec::new();
+                let mut index = 0;
+                let mut found_line = 0;
+                for element in split_string(&output) {
+                    match element {
+                        StringType::Word(word) => {
+                            let style = match smart_case_matches(&query, word) {
+                                true => found_style(index == matched, theme),
+                                false => Style::default(),
+                            };
+                            if index == matched {
+                                found_line = lines.len();
+                            }
+                            spans.push(Span::styled(word, style));
+                            index += 1;
+                        }
+                        StringType::Whitespace(whitespace) => spans.push(Span::raw(whitespace)),
+                        StringType::Tab => spans.push(Span::raw(TAB_STRING)),
+                        StringType::Newline(_) => {
+                            lines.push(Line::from(std::mem::take(&mut spans)))
+                        }
+                    }
+                }
+                lines.push(Line::from(spans));
+                let width = layout.width.saturating_sub(2);
+                // the row the line with the match starts on once the lines before it are wrapped
+                let row = wrapped_line_count(&lines[..found_line], width);
+                if row < scroll_offset || row >= scroll_offset + model.output_scroll.visible_lines {
+                    scroll_offset = row;
+                }
+                model.output_scroll.total_lines = wrapped_line_count(&lines, width);
+                let paragraph = Paragraph::new(lines)
+                    .block(
+                        block
+                            .clone()
+                            .borders(Borders::ALL)
+                            .border_style(output_border),
+                    )
+                    .wrap(Wrap { trim: false });
+                scroll_offset = scroll_offset.min(model.output_scroll.max_offset());
+                safe_render(
+                    frame,
+                    paragraph.scroll((scroll_offset, 0)),
+                    layout,
+                    frame.size().height,
+                );
+            }
+            crate::HintState::HideHints if split_streams.is_some() => {
+                // SAFETY: checked by the guard
+                let (stdout, stderr) = split_streams.unwrap();
+                let areas = Layout::default()
+                    .direction(ratatui::layout::Direction::Vertical)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(layout);
+                let stream_block = |title: &'static str, color| {
+                    Block::new()
+                        .fg(color)
+                        .bg(theme.background)
+                        .bold()
+                        .borders(Borders::ALL)
+                        .border_style(output_border)
+                        .title(Title::from(title).alignment(Alignment::Right))
+                };
+                let stdout = ansi::text(&stdout);
+                model.output_scroll.visible_lines = areas[0].height.saturating_sub(2);
+                model.output_scroll.total_lines =
+                    wrapped_line_count(&stdout.lines, areas[0].width.saturating_sub(2));
+                let stdout = Paragraph::new(stdout)
+                    .block(stream_block(" stdout ", theme.foreground))
+                    .wrap(Wrap { trim: false });
+                safe_render(
+                    frame,
+                    stdout.scroll((scroll_offset, 0)),
+                    areas[0],
+                    frame.size().height,
+                );
+                let stderr = Paragraph::new(ansi::text(&stderr))
+                    .block(stream_block(" stderr ", theme.error))
+                    .wrap(Wrap { trim: false });
+                safe_render(frame, stderr, areas[1], frame.size().height);
             }
             crate::HintState::HideHints => {
+                let text = ansi::text(&output);
+                model.output_scroll.total_lines =
+                    wrapped_line_count(&text.lines, layout.width.saturating_sub(2));
+                let paragraph = Paragraph::new(text)
+                    .block(
+                        block
+                            .clone()
+                            .borders(Borders::ALL)
+                            .border_style(output_border),
+                    )
+                    .wrap(Wrap { trim: false });
                 safe_render(
                     frame,
-                    Paragraph::new(output)
-                        .block(block.clone().borders(Borders::ALL))
-                        .wrap(Wrap { trim: false }),
+                    paragraph.scroll((scroll_offset, 0)),
                     layout,
                     frame.size().height,
                 );
             }
         }
     } else {
+        model.output_scroll.total_lines = 0;
         safe_render(
             frame,
-            block.clone().borders(Borders::ALL),
+            block
+                .clone()
+                .borders(Borders::ALL)
+                .border_style(output_border),
             layout,
             frame.size().height,
         );
     }
 
+    // with hints the rows are only known after rendering, so the match is shown in the next frame
+    if let Some(row) = found_row {
+        if row <= scroll_offset || row > scroll_offset + model.output_scroll.visible_lines {
+            scroll_offset = row - 1;
+        }
+    }
+    // the output may have shrunk since the last scroll
+    model.output_scroll.offset = scroll_offset.min(model.output_scroll.max_offset());
+
+    // only the border, so the colors of the output and the highlighted matches are kept
     safe_render(
         frame,
-        block.clone().borders(Borders::ALL),
+        Block::new()
+            .borders(Borders::ALL)
+            .border_style(output_border.bold()),
         layout,
         frame.size().height,
     );
 
     let animation_x = match origin {
         Some(shell) => {
-            let heading = format!("Output({})", shell);
+            let mut heading = match exit_code {
+                Some(exit_code) => format!("Output({}, exit {})", shell, exit_code),
+                None => format!("Output({})", shell),
+            };
+            match changed {
+                Some(true) => heading.push_str(" ≠ changed"),
+                Some(false) => heading.push_str(" = same"),
+                None => {}
+            }
             safe_render(
                 frame,
                 Paragraph::new(heading.as_str())
@@ -707,12 +1342,12 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                 Rect {
                     x: layout.x,
                     y: layout.y,
-                    width: heading.len() as u16,
+                    width: display_width(&heading),
                     height: 1,
                 },
                 frame.size().height,
             );
-            layout.x + heading.len() as u16
+            layout.x + display_width(&heading)
         }
         None => {
             safe_render(
@@ -732,21 +1367,41 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
         }
     };
 
-    if let Mode::Executing(ref mut direction, ref mut index, _, _) = model.mode {
+    if let Mode::Executing(execution) = &mut model.mode {
+        let label = format!(" {} ", execution.phase.label());
+        safe_render(
+            frame,
+            Paragraph::new(label.as_str()).block(block.clone()),
+            Rect {
+                x: animation_x,
+                y: layout.y,
+                width: (label.len() as u16)
+                    .min((layout.x + layout.width).saturating_sub(animation_x)),
+                height: 1,
+            },
+            frame.size().height,
+        );
+        let animation_x = animation_x + label.len() as u16;
+        let animation_width = (layout.width + layout.x)
+            .saturating_sub(animation_x)
+            .saturating_sub(1);
+        if animation_width == 0 {
+            return;
+        }
         safe_render(
             frame,
             Clear,
             Rect {
                 x: animation_x,
                 y: layout.y,
-                width: layout.width - (animation_x - layout.x) - 1,
+                width: animation_width,
                 height: 1,
             },
             frame.size().height,
         );
 
-        for cell in animation_x..animation_x + layout.width - (animation_x - layout.x) - 1 {
-            if cell == animation_x + *index {
+        for cell in animation_x..animation_x + animation_width {
+            if cell == animation_x + execution.position {
                 safe_render(
                     frame,
                     Paragraph::new("-")
@@ -776,33 +1431,63 @@ fn render_output(frame: &mut ratatui::Frame, model: &mut Model, layout: Rect) {
                 );
             }
         }
-        if *direction {
-            if *index == layout.width - (animation_x - layout.x) - 1 {
-                *direction = false;
+        if execution.forward {
+            if execution.position >= animation_width {
+                execution.forward = false;
             } else {
-                *index += 1;
+                execution.position += 1;
             }
-        } else if *index == 0 {
-            *direction = true;
+        } else if execution.position == 0 {
+            execution.forward = true;
         } else {
-            *index -= 1;
+            execution.position -= 1;
         }
     }
 }
 
+/// The number `:s` takes for an entry of the history pane, with the hint `:sel` takes in front
+/// of it while hints are shown.
+fn history_label(model: &Model, number: usize) -> String {
+    match model.config.hint_state {
+        crate::HintState::ShowHints => format!("{} {}", base10_to_base26(number as u32), number),
+        crate::HintState::HideHints => number.to_string(),
+    }
+}
+
 fn render_command_history(frame: &mut ratatui::Frame, model: &Model, layout: Rect) {
-    let pinned_commands = model
+    let theme = model.config.theme;
+    let inner_height = layout.height.saturating_sub(2);
+    // pins may take up at most half of the pane so the history stays visible
+    let max_pinned_lines = (inner_height / 2) as usize;
+    let mut pinned_commands = model
         .pinned_commands
         .iter()
         .enumerate()
-        .map(|(index, command)| format!("{}: {}", index, command.input))
+        .map(|(index, command)| format!("{}: {}", history_label(model, index), command.input))
+        .map(|pin| match model.config.truncate_history {
+            true => truncate_line(&pin, layout.width.saturating_sub(2) as usize),
+            false => pin,
+        })
         .collect::<Vec<String>>();
+    if pinned_commands.len() > max_pinned_lines {
+        let shown = max_pinned_lines.saturating_sub(1);
+        let hidden = pinned_commands.len() - shown;
+        pinned_commands.truncate(shown);
+        if max_pinned_lines > 0 {
+            pinned_commands.push(format!("+{} more", hidden));
+        }
+    }
+    // the pins and the separator below them
+    let pinned_height = match pinned_commands.is_empty() {
+        true => 0,
+        false => pinned_commands.len() as u16 + 1,
+    };
 
     for (index, command) in pinned_commands.iter().enumerate() {
         safe_render(
             frame,
             Paragraph::new(command.as_str())
-                .block(Block::new().white().on_black())
+                .block(Block::new().fg(theme.foreground).bg(theme.background))
                 .wrap(Wrap { trim: false }),
             Rect {
                 x: layout.x + 1,
@@ -818,7 +1503,12 @@ fn render_command_history(frame: &mut ratatui::Frame, model: &Model, layout: Rec
         safe_render(
             frame,
             ratatui::widgets::Paragraph::new("-".repeat(layout.width as usize - 2))
-                .block(Block::new().white().on_black().bold())
+                .block(
+                    Block::new()
+                        .fg(theme.foreground)
+                        .bg(theme.background)
+                        .bold(),
+                )
                 .wrap(Wrap { trim: false }),
             Rect {
                 x: layout.x + 1,
@@ -831,31 +1521,71 @@ fn render_command_history(frame: &mut ratatui::Frame, model: &Model, layout: Rec
     }
 
     if !model.command_history.is_empty() {
+        let matched = match &model.mode {
+            Mode::Search(search) => Some(search.matched),
+            _ => None,
+        };
+        let mut matched_line = None;
+        let current_directory = std::env::current_dir().ok();
         let commands = model
             .command_history
             .iter()
+            .enumerate()
             .rev()
+            .filter(|(index, _)| model.is_listed(*index, current_directory.as_deref()))
             .enumerate()
-            .map(|(index, command)| format!("{}: {}", index + pinned_commands.len(), command.input))
-            .collect::<Vec<String>>()
-            .join("\n");
+            .map(|(line, (index, command))| {
+                let number = model.command_history.len() - 1 - index + model.pinned_commands.len();
+                let number = match model.config.history_numbers {
+                    true => format!("{} #{}", history_label(model, number), command.number),
+                    false => history_label(model, number),
+                };
+                let mut text = match command.hidden {
+                    true => format!("{}: # {}", number, command.input),
+                    false => format!("{}: {}", number, command.input),
+                };
+                if command.job.is_some() {
+                    text = format!("[bg] {}", text);
+                }
+                if model.config.show_timestamps {
+                    // fixed width so the numbers stay aligned
+                    let timestamp = command
+                        .finished_at
+                        .and_then(|finished_at| finished_at.elapsed().ok())
+                        .map(format_relative_time)
+                        .unwrap_or_default();
+                    text = format!("{:>8} {}", timestamp, text);
+                }
+                if model.config.truncate_history {
+                    text = truncate_line(&text, layout.width.saturating_sub(2) as usize);
+                }
+                match matched == Some(index) {
+                    true => {
+                        matched_line = Some(line as u16);
+                        Line::styled(text, Style::default().reversed())
+                    }
+                    false => Line::from(text),
+                }
+            })
+            .collect::<Vec<Line>>();
+        let history_height = inner_height.saturating_sub(pinned_height);
+        // keep the matched entry in view while searching
+        let scroll = match matched_line {
+            Some(line) if line >= history_height => line + 1 - history_height,
+            _ => 0,
+        };
 
         safe_render(
             frame,
             Paragraph::new(commands)
-                .block(Block::new().white().on_black())
-                .wrap(Wrap { trim: false }),
+                .block(Block::new().fg(theme.foreground).bg(theme.background))
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
             Rect {
                 x: layout.x + 1,
-                y: layout.y
-                    + 1
-                    + pinned_commands.len() as u16
-                    + if pinned_commands.is_empty() { 0 } else { 1 },
+                y: layout.y + 1 + pinned_height,
                 width: layout.width - 2,
-                height: layout.height
-                    - 2
-                    - pinned_commands.len() as u16
-                    - if pinned_commands.is_empty() { 0 } else { 1 },
+                height: history_height,
             },
             frame.size().height,
         );
@@ -863,28 +1593,156 @@ fn render_command_history(frame: &mut ratatui::Frame, model: &Model, layout: Rec
 
     safe_render(
         frame,
-        ratatui::widgets::Paragraph::new("History")
-            .block(Block::new().white().on_black().bold())
-            .wrap(Wrap { trim: false }),
+        ratatui::widgets::Paragraph::new(match model.history_here {
+            true => "History (here)",
+            false => "History",
+        })
+        .block(
+            Block::new()
+                .fg(theme.foreground)
+                .bg(theme.background)
+                .bold(),
+        )
+        .wrap(Wrap { trim: false }),
         layout,
         frame.size().height,
     );
 }
 
+// the delimiter and the number of columns are taken from the first lines only
+const TABLE_SAMPLE_LINES: usize = 5;
+
+/// Splits `text` into rows of cells at tabs, or at whitespace if the first lines contain no
+/// tabs. The first lines determine the number of columns, extra cells are joined into the
+/// last column so e.g. the arguments of commands listed by `ps` stay together.
+fn table_rows(text: &str) -> Vec<Vec<String>> {
+    let lines = text.lines().collect::<Vec<&str>>();
+    let sample = lines.iter().take(TABLE_SAMPLE_LINES);
+    let tab_separated = sample.clone().any(|line| line.contains('\t'));
+    let split = |line: &str| -> Vec<String> {
+        match tab_separated {
+            true => line.split('\t').map(str::to_string).collect(),
+            false => line.split_whitespace().map(str::to_string).collect(),
+        }
+    };
+    let columns = sample
+        .map(|line| split(line).len())
+        .filter(|cells| *cells > 0)
+        .min()
+        .unwrap_or(1);
+    lines
+        .iter()
+        .map(|line| {
+            let mut cells = split(line);
+            if cells.len() > columns {
+                let separator = if tab_separated { "\t" } else { " " };
+                let rest = cells.split_off(columns - 1).join(separator);
+                cells.push(rest);
+            }
+            cells
+        })
+        .collect()
+}
+
+fn column_widths(rows: &[Vec<String>]) -> Vec<u16> {
+    let mut widths = Vec::new();
+    for row in rows {
+        for (column, cell) in row.iter().enumerate() {
+            let width = cell.chars().count() as u16;
+            match widths.get_mut(column) {
+                Some(current) if *current < width => *current = width,
+                Some(_) => {}
+                None => widths.push(width),
+            }
+        }
+    }
+    widths
+}
+
+/// Shortens `text` to `width` columns, marking the cut with an ellipsis.
+fn truncate_line(text: &str, width: usize) -> String {
+    if UnicodeWidthStr::width(text) <= width {
+        return text.to_string();
+    }
+    // the ellipsis takes up one column
+    let mut columns = 1;
+    let mut truncated = text
+        .chars()
+        .take_while(|c| {
+            columns += UnicodeWidthChar::width(*c).unwrap_or(0);
+            columns <= width
+        })
+        .collect::<String>();
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+/// Replaces the home directory at the start of `directory` with `~`.
+fn tilde_directory(directory: &Path, home: &Path) -> String {
+    match directory.strip_prefix(home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.to_string_lossy()),
+        Err(_) => directory.to_string_lossy().to_string(),
+    }
+}
+
+fn common_ancestor(directories: &[PathBuf]) -> Option<PathBuf> {
+    let (first, rest) = directories.split_first()?;
+    let mut ancestor = first.clone();
+    for directory in rest {
+        while !directory.starts_with(&ancestor) {
+            if !ancestor.pop() {
+                return None;
+            }
+        }
+    }
+    Some(ancestor)
+}
+
 fn render_directory_history(frame: &mut ratatui::Frame, model: &Model, layout: Rect) {
+    let theme = model.config.theme;
+    let home = dirs::home_dir();
+    let ancestor = match model.config.directory_display {
+        DirectoryDisplay::CommonAncestor => common_ancestor(&model.directory_history),
+        _ => None,
+    };
     let directories = model
         .directory_history
         .iter()
         .rev()
         .enumerate()
-        .map(|(index, directory)| format!("{}: {}", index, directory.to_string_lossy()))
+        .map(|(index, directory)| {
+            let directory = match (model.config.directory_display, &home, &ancestor) {
+                (DirectoryDisplay::Home, Some(home), _) => tilde_directory(directory, home),
+                (DirectoryDisplay::CommonAncestor, _, Some(ancestor)) => {
+                    match directory.strip_prefix(ancestor) {
+                        Ok(rest) if rest.as_os_str().is_empty() => ".".to_string(),
+                        Ok(rest) => rest.to_string_lossy().to_string(),
+                        Err(_) => directory.to_string_lossy().to_string(),
+                    }
+                }
+                _ => directory.to_string_lossy().to_string(),
+            };
+            format!("{}: {}", index, directory)
+        })
         .collect::<Vec<String>>()
         .join("\n");
+    let title = match &ancestor {
+        Some(ancestor) => format!("Directory History - {}", ancestor.to_string_lossy()),
+        None => "Directory History".to_string(),
+    };
 
     safe_render(
         frame,
         Paragraph::new(directories)
-            .block(Block::new().white().on_black().bold())
+            .block(
+                Block::new()
+                    .fg(theme.foreground)
+                    .bg(theme.background)
+                    .bold(),
+            )
             .wrap(Wrap { trim: false }),
         Rect {
             x: layout.x + 1,
@@ -897,8 +1755,60 @@ fn render_directory_history(frame: &mut ratatui::Frame, model: &Model, layout: R
 
     safe_render(
         frame,
-        ratatui::widgets::Paragraph::new("Directory History")
-            .block(Block::new().white().on_black().bold())
+        ratatui::widgets::Paragraph::new(title)
+            .block(
+                Block::new()
+                    .fg(theme.foreground)
+                    .bg(theme.background)
+                    .bold(),
+            )
+            .wrap(Wrap { trim: false }),
+        layout,
+        frame.size().height,
+    );
+}
+
+fn render_jobs(frame: &mut ratatui::Frame, model: &Model, layout: Rect) {
+    let theme = model.config.theme;
+    let jobs = model
+        .jobs
+        .iter()
+        .enumerate()
+        .map(|(index, job)| {
+            format!(
+                "{}: [{}] {} ({}s)",
+                base10_to_base26(index as u32),
+                job.number,
+                job.input,
+                job.started.elapsed().as_secs()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    safe_render(
+        frame,
+        Paragraph::new(jobs)
+            .block(Block::new().fg(theme.foreground).bg(theme.background))
+            .wrap(Wrap { trim: false }),
+        Rect {
+            x: layout.x + 1,
+            y: layout.y + 1,
+            width: layout.width - 2,
+            height: layout.height - 2,
+        },
+        frame.size().height,
+    );
+
+    safe_render(
+        frame,
+        ratatui::widgets::Paragraph::new("Jobs")
+            .block(
+                Block::new()
+                    .fg(theme.foreground)
+                    .bg(theme.background)
+                    .bold(),
+            )
             .wrap(Wrap { trim: false }),
         layout,
         frame.size().height,
@@ -906,6 +1816,8 @@ fn render_directory_history(frame: &mut ratatui::Frame, model: &Model, layout: R
 }
 
 fn render_directory_view(model: &mut Model, frame: &mut ratatui::Frame) {
+    let theme = model.config.theme;
+    let picker_preview = model.config.picker_preview;
     if let Mode::Directory(directory) = &mut model.mode {
         fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
             let popup_layout = Layout::default()
@@ -930,35 +1842,67 @@ fn render_directory_view(model: &mut Model, frame: &mut ratatui::Frame) {
         let mut items = directory
             .children
             .iter()
-            .map(|child| {
+            .enumerate()
+            .map(|(index, child)| {
                 let item = ListItem::new(Line::from(child.to_string()));
-                match child {
-                    File::Directory(_) => {
-                        item.style(Style::default().fg(ratatui::style::Color::Green))
-                    }
-                    File::File(_) => item.style(Style::default().fg(ratatui::style::Color::White)),
+                let style = match child {
+                    File::Directory(_) => Style::default().fg(theme.directory),
+                    File::File(_) => Style::default().fg(theme.foreground),
+                };
+                match directory.selected == Some(index) {
+                    true => item.style(style.reversed()),
+                    false => item.style(style),
                 }
             })
             .collect::<Vec<ListItem>>();
         items.insert(
             0,
-            ListItem::new(Line::from(".."))
-                .style(Style::default().fg(ratatui::style::Color::Green)),
+            ListItem::new(Line::from("..")).style(Style::default().fg(theme.directory)),
         );
         items.insert(
             0,
-            ListItem::new(Line::from(".")).style(Style::default().fg(ratatui::style::Color::Green)),
+            ListItem::new(Line::from(".")).style(Style::default().fg(theme.directory)),
         );
 
-        let area = centered_rect(40, 50, frame.size());
+        let (area, preview_area) = if picker_preview {
+            let areas = Layout::default()
+                .direction(ratatui::layout::Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(centered_rect(80, 50, frame.size()));
+            (areas[0], Some(areas[1]))
+        } else {
+            (centered_rect(40, 50, frame.size()), None)
+        };
+
+        if let Some(preview_area) = preview_area {
+            let preview = match &directory.preview {
+                Some(Preview::Text(text)) => text.as_str(),
+                Some(Preview::Binary) => "binary file",
+                Some(Preview::Unreadable(error)) => error.as_str(),
+                None => "",
+            };
+            safe_render(frame, Clear, preview_area, frame.size().height);
+            safe_render(
+                frame,
+                Paragraph::new(preview).block(
+                    Block::new()
+                        .fg(theme.foreground)
+                        .bg(theme.background)
+                        .borders(Borders::ALL)
+                        .title("Preview"),
+                ),
+                preview_area,
+                frame.size().height,
+            );
+        }
 
         safe_render(frame, Clear, area, frame.size().height);
 
         safe_render(
             frame,
             Block::new()
-                .white()
-                .on_black()
+                .fg(theme.foreground)
+                .bg(theme.background)
                 .bold()
                 .borders(ratatui::widgets::Borders::ALL)
                 .title_alignment(ratatui::layout::Alignment::Center)
@@ -995,7 +1939,12 @@ fn render_directory_view(model: &mut Model, frame: &mut ratatui::Frame) {
         };
         safe_render(
             frame,
-            ratatui::widgets::List::new(items).block(Block::new().white().on_black().bold()),
+            ratatui::widgets::List::new(items).block(
+                Block::new()
+                    .fg(theme.foreground)
+                    .bg(theme.background)
+                    .bold(),
+            ),
             list_location,
             frame.size().height,
         );
@@ -1005,7 +1954,355 @@ fn render_directory_view(model: &mut Model, frame: &mut ratatui::Frame) {
 
 #[cfg(test)]
 mod tests {
-    use crate::{split_string, StringType};
+    use ratatui::{
+        backend::TestBackend,
+        buffer::Buffer,
+        style::{Color, Modifier},
+        text::Text,
+        Terminal,
+    };
+
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        base10_to_base26, column_widths, common_ancestor, display_width, split_index_for_width,
+        table_rows, tilde_directory, truncate_line, wrapped_line_count,
+    };
+    use crate::{
+        split_string, CommandWithoutOutput, CompletedCommand, CurrentView, HintState, Mode, Model,
+        Output, OutputSearch, OutputType, Status, StringType,
+    };
+
+    fn test_model() -> Model {
+        Model {
+            directory_history: vec![PathBuf::from("/tmp")],
+            ..Default::default()
+        }
+    }
+
+    fn draw(model: &mut Model, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| super::view(model, frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn render(model: &mut Model, width: u16, height: u16) -> Vec<String> {
+        let buffer = draw(model, width, height);
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// The columns `from..to` of a rendered row.
+    fn columns(row: &str, from: usize, to: usize) -> String {
+        row.chars().skip(from).take(to - from).collect()
+    }
+
+    #[test]
+    fn test_truncate_line() {
+        assert_eq!(truncate_line("ls -la", 6), "ls -la");
+        assert_eq!(truncate_line("cargo build --release", 8), "cargo b…");
+        assert_eq!(truncate_line("ü ü ü", 3), "ü …");
+        assert_eq!(truncate_line("ls", 0), "");
+        // wide characters take two columns
+        assert_eq!(truncate_line("日本語のファイル", 6), "日本…");
+        assert_eq!(truncate_line("日本語", 6), "日本語");
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let rows = table_rows("PID CMD\n1   init --flag\n");
+        assert_eq!(
+            rows,
+            vec![
+                vec!["PID".to_string(), "CMD".to_string()],
+                vec!["1".to_string(), "init --flag".to_string()],
+            ]
+        );
+        assert_eq!(column_widths(&rows), vec![3, 11]);
+
+        let rows = table_rows("a b\tc\nd\t\n");
+        assert_eq!(
+            rows,
+            vec![
+                vec!["a b".to_string(), "c".to_string()],
+                vec!["d".to_string(), "".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directory_display() {
+        let home = PathBuf::from("/home/user");
+        assert_eq!(tilde_directory(&home, &home), "~");
+        assert_eq!(
+            tilde_directory(Path::new("/home/user/code/vshell"), &home),
+            "~/code/vshell"
+        );
+        assert_eq!(
+            tilde_directory(Path::new("/home/username"), &home),
+            "/home/username"
+        );
+        assert_eq!(
+            common_ancestor(&[
+                PathBuf::from("/home/user/code/vshell/src"),
+                PathBuf::from("/home/user/code/other"),
+                PathBuf::from("/home/user/code"),
+            ]),
+            Some(PathBuf::from("/home/user/code"))
+        );
+        assert_eq!(common_ancestor(&[]), None);
+    }
+
+    #[test]
+    fn test_pinned_commands_do_not_cover_history() {
+        let mut model = test_model();
+        for index in 0..20 {
+            model.pinned_commands.push(CommandWithoutOutput {
+                input: format!("pinned{}", index),
+                cursor_position: 0,
+            });
+        }
+        model.command_history.push(CompletedCommand {
+            input: "latest".to_string(),
+            ..Default::default()
+        });
+        let content = render(&mut model, 60, 24).join("\n");
+        assert!(content.contains("+16 more"));
+        assert!(content.contains("20: latest"));
+    }
+
+    #[test]
+    fn test_hints_keep_indentation() {
+        let mut model = test_model();
+        model.config.hint_state = HintState::ShowHints;
+        model.current_command = CurrentView::Output(Output {
+            output_type: OutputType::Success("x:\n    y\n  - z".to_string(), String::new(), None),
+            ..Default::default()
+        });
+        let rows = render(&mut model, 60, 24);
+        let first = rows.iter().position(|row| row.contains("a:x:")).unwrap();
+        let column = rows[first].find("a:x:").unwrap();
+        assert!(rows[first + 1][column..].starts_with("    b:y "));
+        assert!(rows[first + 2][column..].starts_with("  c:- d:z "));
+    }
+
+    #[test]
+    fn test_cursor_at_end_of_full_input() {
+        let mut model = test_model();
+        model.config.hint_state = HintState::HideHints;
+        // the input pane takes the top left quarter, minus its borders
+        let (columns, rows) = (28, 10);
+        for length in [columns, columns * rows] {
+            let input = "a".repeat(length as usize);
+            model.set_current_view_from_command(input.len() as u64, input);
+            let buffer = draw(&mut model, 60, 24);
+            let cursor = (1..=rows)
+                .flat_map(|y| (1..=columns).map(move |x| (x, y)))
+                .filter(|(x, y)| buffer.get(*x, *y).bg == Color::Green)
+                .collect::<Vec<(u16, u16)>>();
+            assert_eq!(
+                cursor.len(),
+                1,
+                "no cursor for an input of length {}",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn test_ansi_colors_in_output() {
+        let mut model = Model {
+            current_command: CurrentView::Output(Output {
+                output_type: OutputType::Success(
+                    "\x1b[34mblue\x1b[0m plain".to_string(),
+                    String::new(),
+                    Some(0),
+                ),
+                ..Default::default()
+            }),
+            ..test_model()
+        };
+        model.config.hint_state = HintState::HideHints;
+        let buffer = draw(&mut model, 60, 24);
+        assert_eq!(buffer.get(31, 1).symbol(), "b");
+        assert_eq!(buffer.get(31, 1).fg, Color::Blue);
+        assert_eq!(buffer.get(36, 1).symbol(), "p");
+        assert_eq!(buffer.get(36, 1).fg, Color::White);
+    }
+
+    #[test]
+    fn test_split_output() {
+        let mut model = Model {
+            current_command: CurrentView::Output(Output {
+                output_type: OutputType::Success("out".to_string(), "err".to_string(), Some(0)),
+                ..Default::default()
+            }),
+            ..test_model()
+        };
+        model.config.hint_state = HintState::HideHints;
+        model.config.split_output = true;
+        let buffer = draw(&mut model, 60, 24);
+        // the output pane is the right half, stderr starts in the middle of it
+        assert_eq!(buffer.get(31, 1).symbol(), "o");
+        assert_eq!(buffer.get(31, 13).symbol(), "e");
+        assert_eq!(buffer.get(31, 13).fg, Color::Red);
+    }
+
+    #[test]
+    fn test_find_in_output() {
+        let lines = (0..40)
+            .map(|line| format!("row{}", line))
+            .collect::<Vec<String>>();
+        let mut model = Model {
+            current_command: CurrentView::Output(Output {
+                output_type: OutputType::Success(lines.join("\n"), String::new(), Some(0)),
+                ..Default::default()
+            }),
+            mode: Mode::Find(OutputSearch {
+                query: "row3".to_string(),
+                matched: 30,
+            }),
+            ..test_model()
+        };
+        model.config.hint_state = HintState::HideHints;
+        let buffer = draw(&mut model, 60, 24);
+
+        // the match is scrolled into view, as far as the end of the output allows
+        assert_eq!(model.output_scroll.offset, 18);
+        assert_eq!(buffer.get(31, 13).symbol(), "r");
+        assert_eq!(buffer.get(31, 13).bg, Color::Cyan);
+        // the other matches are highlighted as well
+        assert_eq!(buffer.get(35, 14).symbol(), "1");
+        assert!(buffer.get(35, 14).modifier.contains(Modifier::REVERSED));
+        assert!(!buffer.get(31, 2).modifier.contains(Modifier::REVERSED));
+        // `row3` and `row30` to `row39` match, `row30` is the second
+        let rows = render(&mut model, 60, 24);
+        assert_eq!(columns(&rows[0], 45, 59), " 2/11 matches ");
+    }
+
+    #[test]
+    fn test_history_hints() {
+        let mut model = test_model();
+        for input in ["ls", "date"] {
+            model.command_history.push(CompletedCommand {
+                input: input.to_string(),
+                ..Default::default()
+            });
+        }
+        model.command_history_index = 2;
+        model.config.hint_state = HintState::ShowHints;
+        let rows = render(&mut model, 60, 24);
+        assert_eq!(columns(&rows[13], 1, 29).trim(), "a 0: date");
+        assert_eq!(columns(&rows[14], 1, 29).trim(), "b 1: ls");
+    }
+
+    #[test]
+    fn test_base10_to_base26() {
+        let hints = [0, 25, 26, 27, 51, 52, 53, 702].map(base10_to_base26);
+        assert_eq!(hints, ["a", "z", "aa", "ab", "az", "ba", "bb", "aaa"]);
+        assert_eq!(base10_to_base26(u32::MAX), "mwlqkwv");
+    }
+
+    #[test]
+    fn test_terminal_too_small() {
+        for (width, height) in [(10, 30), (80, 3), (1, 1), (0, 0)] {
+            render(&mut test_model(), width, height);
+        }
+        let rows = render(&mut test_model(), 18, 4);
+        assert_eq!(rows[0], "terminal too small");
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("ls"), 2);
+        assert_eq!(display_width("日本"), 4);
+        // e followed by a combining acute accent
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(split_index_for_width("日本語", 3), "日".len());
+        assert_eq!(split_index_for_width("e\u{301}x", 1), "e\u{301}".len());
+        assert_eq!(split_index_for_width("ls", 5), 2);
+    }
+
+    #[test]
+    fn test_status_with_wide_characters() {
+        let mut model = Model {
+            status: Some(Status::Error("ファイル".to_string())),
+            ..test_model()
+        };
+        let buffer = draw(&mut model, 60, 24);
+        // four characters that are two columns wide each, the error style ends with them
+        assert_eq!(buffer.get(7, 23).symbol(), "ル");
+        assert_eq!(buffer.get(7, 23).fg, Color::Red);
+        assert_ne!(buffer.get(9, 23).fg, Color::Red);
+    }
+
+    #[test]
+    fn test_wrapped_line_count() {
+        let count = |text: &str, width| wrapped_line_count(&Text::from(text).lines, width);
+        assert_eq!(count("", 10), 1);
+        assert_eq!(count("hello world", 20), 1);
+        // the space at the wrap is dropped
+        assert_eq!(count("hello world", 5), 2);
+        // words longer than the width are broken
+        assert_eq!(count("abcdefghij", 4), 3);
+        assert_eq!(count("日本語", 4), 2);
+        assert_eq!(count("a\n\nb c", 1), 4);
+        assert_eq!(count("a\nb", 0), 0);
+    }
+
+    #[test]
+    fn test_wrap_input_at_word_boundary() {
+        let mut model = test_model();
+        model.config.hint_state = HintState::HideHints;
+        // the input pane is 28 columns wide, the path doesn't fit behind `cat `
+        let input = format!("cat {}", "b".repeat(25));
+        model.set_current_view_from_command(4, input);
+        let buffer = draw(&mut model, 60, 24);
+        assert_eq!(buffer.get(5, 1).symbol(), " ");
+        assert_eq!(buffer.get(1, 2).symbol(), "b");
+        assert_eq!(buffer.get(25, 2).symbol(), "b");
+        let cursor = (1..=10)
+            .flat_map(|y| (1..=28).map(move |x| (x, y)))
+            .filter(|(x, y)| buffer.get(*x, *y).bg == Color::Green)
+            .collect::<Vec<(u16, u16)>>();
+        assert_eq!(cursor, [(1, 2)]);
+    }
+
+    #[test]
+    fn test_cursor_after_wide_characters() {
+        let mut model = test_model();
+        model.config.hint_state = HintState::HideHints;
+        let input = "echo 日本".to_string();
+        model.set_current_view_from_command(input.len() as u64, input);
+        let buffer = draw(&mut model, 60, 24);
+        let cursor = (1..=28)
+            .filter(|x| buffer.get(*x, 1).bg == Color::Green)
+            .collect::<Vec<u16>>();
+        // "echo " and two characters that are two columns wide each
+        assert_eq!(cursor, [1 + 5 + 4]);
+    }
+
+    #[test]
+    fn test_wrap_long_multibyte_word() {
+        let mut model = test_model();
+        model.set_current_view_from_command(0, "äöü€".repeat(20));
+        let rows = render(&mut model, 30, 20);
+
+        // the inside of the input pane is 13 columns wide, the word starts after its hint
+        assert_eq!(columns(&rows[1], 1, 14), "a:äöü€äöü€äöü");
+        assert_eq!(columns(&rows[2], 1, 14), "€äöü€äöü€äöü€");
+        assert_eq!(columns(&rows[6], 1, 14), "€äöü€äöü€äöü€");
+        assert_eq!(columns(&rows[7], 1, 14).trim_end(), "äöü€");
+        assert!(matches!(
+            model.current_command,
+            CurrentView::CommandWithoutOutput(_)
+        ));
+    }
 
     #[test]
     fn test_single_word() {